@@ -1,30 +1,96 @@
 use anyhow::{bail, Context, Ok, Result};
-use clap::{CommandFactory, Parser};
+use clap::{CommandFactory, Parser, ValueEnum};
 use core::fmt;
-use dialoguer::{theme::ColorfulTheme, Select};
+use dialoguer::{
+    theme::{ColorfulTheme, SimpleTheme, Theme},
+    Confirm, Select,
+};
+use futures::stream::{self, StreamExt};
 use regex::Regex;
-use reqwest::Response;
+use reqwest::{Client, Response};
 use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
 use std::{
-    env::consts,
+    collections::{BTreeMap, HashMap},
+    env::{self, consts},
+    ffi::OsString,
     fs::{self, File},
-    io::{self, Cursor},
+    io::{self, Cursor, IsTerminal, Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     path::{Path, PathBuf},
+    process::{self, Command},
     str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+    time::{Duration, Instant, SystemTime},
 };
+use tempdir::TempDir;
 use zip::ZipArchive;
 
+/// Set when stdout carries data, so that progress messages go to stderr instead.
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// When the shared `--max-rate` budget is next free, so that concurrent downloads stay under one cap.
+static THROTTLE_NEXT: Mutex<Option<Instant>> = Mutex::new(None);
+
+macro_rules! status {
+    ($($arg:tt)*) => {
+        match STATUS_TO_STDERR.load(Ordering::Relaxed) {
+            true => eprintln!($($arg)*),
+            false => println!($($arg)*),
+        }
+    };
+}
+
 #[cfg(unix)]
 use std::os::unix::prelude::PermissionsExt;
 
 const TERRAFORM_ARCHIVE_URL: &str = "https://releases.hashicorp.com/terraform";
+const TERRAFORM_RELEASES_URL: &str = "https://api.releases.hashicorp.com/v1/releases/terraform";
 const OPENTOFU_ARCHIVE_URL: &str = "https://github.com/opentofu/opentofu/releases/download";
+const OPENTOFU_RELEASES_URL: &str = "https://api.github.com/repos/opentofu/opentofu/releases";
+const OPENTOFU_SIGNING_ISSUER: &str = "https://token.actions.githubusercontent.com";
+const SELF_LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/ASleepyCat/tfswitcher/releases/latest";
+const USER_AGENT: &str = concat!(clap::crate_name!(), "/", clap::crate_version!());
 const CONFIG_FILE_NAME: &str = ".tfswitch.toml";
+const TOOL_VERSIONS_FILE_NAME: &str = ".tool-versions";
+const TERRAGRUNT_FILE_NAME: &str = "terragrunt.hcl";
+const XDG_CONFIG_FILE_NAME: &str = "config.toml";
+const ALIASES_FILE_NAME: &str = "aliases.toml";
+const APP_DIR_NAME: &str = "tfswitcher";
 const DEFAULT_LOCATION: &str = ".local/bin";
-const DEFAULT_CACHE_LOCATION: &str = ".cache/tfswitcher";
+const DEFAULT_CACHE_LOCATION: &str = ".cache";
+const DEFAULT_CONFIG_LOCATION: &str = ".config";
+const CACHE_MANIFEST_FILE_NAME: &str = "SHA256SUMS";
+const ENV_FILE_NAME: &str = ".env";
+const TAR_BLOCK_SIZE: usize = 512;
+const BINARY_CACHE_DIR_NAME: &str = "binaries";
+const TEMP_FILE_PREFIX: &str = ".tfswitcher-tmp-";
+/// Temporary files older than this are left over from an interrupted run.
+const STALE_TEMP_FILE_AGE: Duration = Duration::from_secs(10 * 60);
+const DEFAULT_JOBS: usize = 4;
+const DEFAULT_MODE: u32 = 0o755;
+const MAX_HTTP_ATTEMPTS: u32 = 3;
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+const MAX_PROXY_REQUEST_HEAD: usize = 64 * 1024;
+const UNIX_PROXY_USER: &str = "tfswitcher";
+const DEFAULT_ARCHIVE_NAME_TEMPLATE: &str = "{product}_{version}_{os}_{arch}.zip";
+
+/// Platforms that Terraform and OpenTofu publish release archives for
+const SUPPORTED_PLATFORMS: &[(&str, &[&str])] = &[
+    ("darwin", &["amd64", "arm64"]),
+    ("freebsd", &["386", "amd64", "arm"]),
+    ("linux", &["386", "amd64", "arm", "arm64"]),
+    ("openbsd", &["386", "amd64"]),
+    ("solaris", &["amd64"]),
+    ("windows", &["386", "amd64"]),
+];
 
-#[derive(Parser, Default, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Parser, Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
 #[command(version, about)]
 struct Args {
     /// Location of terraform binary
@@ -42,22 +108,655 @@ struct Args {
     #[serde(default)]
     opentofu: bool,
 
+    /// Verify downloaded archives against the published SHA256SUMS
+    #[arg(long)]
+    #[serde(default)]
+    verify: bool,
+
+    /// Fail immediately on checksum mismatch instead of re-downloading once
+    #[arg(long)]
+    #[serde(default)]
+    strict: bool,
+
+    /// How to install the binary [default: overwrite]
+    #[arg(long, value_enum)]
+    install_mode: Option<InstallMode>,
+
+    /// Only connect to servers over IPv4
+    #[arg(long, conflicts_with = "ipv6")]
+    #[serde(default)]
+    ipv4: bool,
+
+    /// Only connect to servers over IPv6
+    #[arg(long)]
+    #[serde(default)]
+    ipv6: bool,
+
+    /// List available versions and exit
+    #[arg(long)]
+    #[serde(skip)]
+    list: bool,
+
+    /// Output format for listing versions
+    #[arg(long, value_enum, default_value_t, requires = "list")]
+    #[serde(skip)]
+    format: ListFormat,
+
+    /// Install from a local archive instead of downloading it
+    #[arg(long, value_name = "PATH")]
+    #[serde(skip)]
+    from_zip: Option<PathBuf>,
+
+    /// Expected SHA256 or SHA512 checksum of the archive passed to --from-zip
+    #[arg(long, requires = "from_zip")]
+    #[serde(skip)]
+    checksum: Option<String>,
+
+    /// Only list versions newer than the given version
+    #[arg(long, value_name = "VERSION", requires = "list")]
+    #[serde(skip)]
+    since: Option<String>,
+
+    /// Limit the prompt and list to the N newest versions
+    #[arg(long, value_name = "N")]
+    count: Option<usize>,
+
+    /// Template for archive file names, supports {product}, {version}, {os}, {arch} and {libc}
+    #[arg(long, value_name = "TEMPLATE")]
+    archive_name_template: Option<String>,
+
+    /// Check whether a newer version of tfswitcher is available
+    #[arg(long)]
+    #[serde(default)]
+    check_self_update: bool,
+
+    /// Install the newest version matching a version constraint
+    #[arg(long, env = "TF_VERSION_CONSTRAINT")]
+    constraint: Option<String>,
+
+    /// Check that the installed binary matches the official release archive
+    #[arg(long)]
+    #[serde(skip)]
+    audit: bool,
+
+    /// Print the download URL of the resolved version and exit
+    #[arg(long)]
+    #[serde(skip)]
+    print_url: bool,
+
+    /// Permission bits of the installed binary in octal, Unix only [default: 755]
+    #[arg(long, value_parser = parse_mode)]
+    mode: Option<u32>,
+
+    /// Fail instead of prompting when no version is pinned
+    #[arg(long)]
+    #[serde(default)]
+    require_pin: bool,
+
+    /// Send HTTP requests through a proxy listening on a Unix domain socket, Unix only
+    #[arg(long, value_name = "SOCKET", conflicts_with_all = ["ipv4", "ipv6"])]
+    unix_proxy: Option<PathBuf>,
+
+    /// Install every version=path entry listed in a manifest file
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["list", "from_zip", "audit", "print_url"])]
+    #[serde(skip)]
+    batch: Option<PathBuf>,
+
+    /// Limit archive downloads to the given number of bytes per second, shared by concurrent
+    /// downloads
+    #[arg(long, value_name = "BYTES", value_parser = clap::value_parser!(u64).range(1..))]
+    max_rate: Option<u64>,
+
+    /// Prompt for a version when a constraint matches more than one
+    #[arg(long)]
+    #[serde(default)]
+    interactive_match: bool,
+
+    /// Print the version a constraint resolves to and exit
+    #[arg(long, value_name = "CONSTRAINT", conflicts_with_all = ["list", "batch"])]
+    #[serde(skip)]
+    resolve_constraint: Option<String>,
+
+    /// Template for the install path, supports {product}, {version}, {os}, {arch} and a leading ~
+    #[arg(long, value_name = "TEMPLATE")]
+    install_path_template: Option<String>,
+
+    /// Reinstall the resolved version from the cached archive without downloading it
+    #[arg(long, conflicts_with_all = ["from_zip", "batch", "audit"])]
+    #[serde(skip)]
+    reinstall: bool,
+
+    /// Directory to cache downloaded archives in [default: $XDG_CACHE_HOME/tfswitcher]
+    #[arg(long, value_name = "DIR", env = "TFSWITCHER_CACHE_DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// List the os/arch platforms a version was published for and exit
+    #[arg(long, value_name = "VERSION", conflicts_with_all = ["list", "batch"])]
+    #[serde(skip)]
+    list_os_arch: Option<String>,
+
+    /// Show release dates in the version prompt
+    #[arg(long)]
+    #[serde(default)]
+    show_dates: bool,
+
+    /// Save the given version under an alias that can be installed later as @ALIAS
+    #[arg(long, value_name = "ALIAS", requires = "install_version")]
+    #[serde(skip)]
+    alias: Option<String>,
+
+    /// Download the resolved version into the cache without installing it
+    #[arg(long, conflicts_with_all = ["list", "batch", "from_zip", "audit", "reinstall"])]
+    #[serde(skip)]
+    download_only: bool,
+
+    /// Ignore version files, terragrunt.hcl and module constraints in the current directory
+    #[arg(long)]
+    #[serde(default)]
+    no_module_detect: bool,
+
+    /// User-Agent header sent with HTTP requests [default: tfswitcher/<version>]
+    #[arg(long, value_name = "USER_AGENT")]
+    user_agent: Option<String>,
+
+    /// Verify the signature of OpenTofu checksums with cosign, which must be installed
+    #[arg(long)]
+    #[serde(default)]
+    cosign: bool,
+
+    /// Print the effective configuration after merging flags and config files and exit
+    #[arg(long)]
+    #[serde(skip)]
+    print_config: bool,
+
+    /// Replace the target of a symlinked binary instead of refusing to write through it
+    #[arg(long)]
+    #[serde(default)]
+    follow_symlink: bool,
+
+    /// Base URL of a mirror to download archives from, may be given several times
+    #[arg(long, value_name = "URL")]
+    #[serde(default)]
+    mirror_url: Vec<String>,
+
+    /// Download from whichever of the mirrors responds fastest instead of the first one
+    #[arg(long)]
+    #[serde(default)]
+    pick_fastest_mirror: bool,
+
+    /// Fail instead of prompting, implied when stdin is not a terminal
+    #[arg(long)]
+    #[serde(default)]
+    no_prompt: bool,
+
+    /// Binary name to look for in $PATH, may be given several times [default: the program's name]
+    #[arg(long = "program-name", value_name = "NAME")]
+    #[serde(default, rename = "program_name")]
+    program_names: Vec<String>,
+
+    /// Remove all cached archives
+    #[arg(long, conflicts_with_all = ["list", "batch", "uninstall"])]
+    #[serde(skip)]
+    prune: bool,
+
+    /// Remove the versioned binary and cached archive of a version
+    #[arg(long, value_name = "VERSION", conflicts_with_all = ["list", "batch"])]
+    #[serde(skip)]
+    uninstall: Option<String>,
+
+    /// Bundle the cached archives, binaries and manifest into a tarball
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["list", "batch", "uninstall", "prune"])]
+    #[serde(skip)]
+    export_cache: Option<PathBuf>,
+
+    /// Unpack a tarball written by --export-cache into the cache directory
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["list", "batch", "uninstall", "prune", "export_cache"]
+    )]
+    #[serde(skip)]
+    import_cache: Option<PathBuf>,
+
+    /// Skip confirmation of actions that remove or overwrite files
+    #[arg(short = 'y', long)]
+    #[serde(skip)]
+    yes: bool,
+
+    /// Show what --prune or --uninstall would remove without removing anything
+    #[arg(long)]
+    #[serde(skip)]
+    dry_run: bool,
+
+    /// Operating system to download builds for [default: the current one]
+    #[arg(long, value_name = "OS")]
+    os: Option<String>,
+
+    /// Architecture to download builds for [default: the current one]
+    #[arg(long, value_name = "ARCH")]
+    arch: Option<String>,
+
+    /// Install the Nth newest version, where 0 is the latest
+    #[arg(
+        long,
+        value_name = "N",
+        allow_negative_numbers = true,
+        conflicts_with_all = ["install_version", "constraint"]
+    )]
+    #[serde(skip)]
+    install_index: Option<i64>,
+
+    /// Emit progress events to stderr in the given format
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    #[serde(skip)]
+    events: Option<EventFormat>,
+
+    /// Minimum TLS version to accept when connecting to servers
+    #[arg(long, value_enum, value_name = "VERSION")]
+    min_tls: Option<TlsVersion>,
+
+    /// Read the required_version constraint from this HCL or JSON file instead of the module
+    #[arg(long, value_name = "FILE", conflicts_with = "constraint")]
+    constraint_file: Option<PathBuf>,
+
+    /// Disable colored output, also disabled by setting NO_COLOR
+    #[arg(long)]
+    #[serde(default)]
+    no_color: bool,
+
+    /// Write the binary to stdout instead of installing it
+    #[arg(
+        long,
+        conflicts_with_all = ["list", "batch", "download_only", "print_url", "from_zip", "audit"]
+    )]
+    #[serde(skip)]
+    stdout: bool,
+
+    /// Show versions added or removed since the last listing
+    #[arg(long, conflicts_with_all = ["list", "batch"])]
+    #[serde(skip)]
+    compare: bool,
+
+    /// Refuse to install versions older than this one
+    #[arg(long, value_name = "VERSION")]
+    min_version: Option<String>,
+
+    /// Keep extracted binaries in the cache and copy them on later installs
+    #[arg(long)]
+    #[serde(default)]
+    cache_binaries: bool,
+
+    /// Install the newest version on a release channel
+    #[arg(long, value_enum, conflicts_with_all = ["install_version", "constraint", "install_index"])]
+    #[serde(skip)]
+    channel: Option<Channel>,
+
+    /// Also read the version from this environment variable, after TF_VERSION
+    #[arg(long, value_name = "NAME")]
+    version_env: Option<String>,
+
+    /// Also extract every other executable in the archive next to the binary
+    #[arg(long)]
+    #[serde(default)]
+    extract_all: bool,
+
+    /// Download the resolved version into the cache for each os/arch pair without installing it
+    #[arg(
+        long,
+        value_name = "OS/ARCH",
+        value_delimiter = ',',
+        conflicts_with_all = ["list", "batch", "stdout"]
+    )]
+    #[serde(skip)]
+    targets: Vec<String>,
+
+    /// Format of the install summary and errors, json prints them as JSON objects
+    #[arg(long, value_enum, default_value_t)]
+    #[serde(skip)]
+    output: OutputFormat,
+
+    /// URL of a JSON or TOML policy with the version constraint allowed for each program
+    #[arg(long, value_name = "URL")]
+    policy_url: Option<String>,
+
+    /// The constraint fetched from --policy-url, applied on top of every resolution
+    #[arg(skip)]
+    #[serde(skip)]
+    policy_constraint: Option<String>,
+
+    /// Print the SHA256 checksum of a version's archive in the SHA256SUMS format and exit
+    #[arg(long, value_name = "VERSION", conflicts_with_all = ["list", "batch"])]
+    #[serde(skip)]
+    print_checksum: Option<String>,
+
+    /// Maximum number of simultaneous downloads for --batch and --targets [default: 4]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    jobs: Option<u64>,
+
+    /// Read the version from a `# terraform-version: <version>` comment in this file
+    #[arg(long, value_name = "FILE")]
+    version_annotation: Option<PathBuf>,
+
+    /// Fail instead of skipping verification when a release has no checksums file
+    #[arg(long)]
+    #[serde(default)]
+    require_checksum: bool,
+
+    /// Name of the archive entry to extract [default: the program name]
+    #[arg(long, value_name = "NAME")]
+    entry_name: Option<String>,
+
+    /// Install the stable release just before the currently installed version
+    #[arg(long, conflicts_with_all = ["install_version", "constraint", "install_index", "channel"])]
+    #[serde(skip)]
+    rollback: bool,
+
+    /// Check that the installed binary is the resolved version without installing anything
+    #[arg(long, conflicts_with_all = ["audit", "reinstall", "rollback"])]
+    #[serde(skip)]
+    verify_only: bool,
+
+    /// With --list, only list versions with an archive in the cache, without going online
+    #[arg(long, requires = "list")]
+    #[serde(skip)]
+    cached_only: bool,
+
+    /// PEM certificate to trust in addition to the system roots, may be given several times
+    #[arg(long, value_name = "PATH", env = "TFSWITCHER_CA_CERT")]
+    #[serde(default)]
+    ca_cert: Vec<PathBuf>,
+
+    /// Accept invalid TLS certificates, e.g. for a test mirror with a self-signed certificate
+    #[arg(long)]
+    #[serde(skip)]
+    insecure_skip_tls_verify: bool,
+
+    /// Shell command to run before downloading the resolved version, a failure aborts the install
+    #[arg(long, value_name = "COMMAND")]
+    pre_download: Option<String>,
+
+    /// Print the URL of the release notes for the installed version
+    #[arg(long)]
+    #[serde(skip)]
+    show_changelog_url: bool,
+
+    /// Only consider versions released within this many days or weeks, e.g. 90d or 12w
+    #[arg(long, value_name = "DURATION", value_parser = parse_days)]
+    #[serde(skip)]
+    newer_than: Option<u64>,
+
+    /// Run the installed binary with the arguments after `--` once the install is done
+    #[arg(long, conflicts_with_all = [
+        "verify_only", "batch", "download_only", "stdout", "targets", "list", "uninstall", "audit"
+    ])]
+    #[serde(skip)]
+    exec: bool,
+
+    /// C library to substitute for {libc} in the archive name template [default: detected]
+    #[arg(long, value_enum)]
+    libc: Option<Libc>,
+
+    /// Version to pre-select in the prompt [default: the newest]
+    #[arg(long, value_name = "VERSION")]
+    default_version: Option<String>,
+
+    /// Read the version from a `TERRAFORM_VERSION=<version>` line in this .env file or Makefile
+    /// [default: .env if present]
+    #[arg(long, value_name = "FILE")]
+    env_file: Option<PathBuf>,
+
+    /// Variable to read from the env file [default: TERRAFORM_VERSION, or TOFU_VERSION]
+    #[arg(long, value_name = "NAME")]
+    env_file_key: Option<String>,
+
+    /// Extra HTTP status codes to retry on, e.g. 502,503 for a flaky mirror
+    #[arg(
+        long,
+        value_name = "CODES",
+        value_delimiter = ',',
+        value_parser = parse_retry_status
+    )]
+    #[serde(default)]
+    retry_on: Vec<u16>,
+
     #[arg(env = "TF_VERSION")]
     #[serde(rename = "version")]
     install_version: Option<String>,
 
+    /// Arguments passed to the binary with --exec
+    #[arg(last = true, value_name = "ARGS", requires = "exec")]
+    #[serde(skip)]
+    exec_args: Vec<String>,
+
     /// Generate tab-completion scripts for the specified shell
     #[arg(short = 'c', long = "completions", id = "SHELL")]
     #[serde(skip)]
     generator: Option<clap_complete::Shell>,
 }
 
+/// Categories of failures. These are carried inside `anyhow::Error` so that context can still be
+/// added on top, and the category is recovered with `downcast_ref`.
+#[derive(Debug, thiserror::Error)]
+enum TfswitcherError {
+    #[error("{message}")]
+    Http {
+        message: String,
+        source: reqwest::Error,
+    },
+    #[error("{0}")]
+    VersionNotFound(String),
+    #[error("{message}")]
+    Io { message: String, source: io::Error },
+    #[error("failed to parse version constraint {constraint}")]
+    InvalidConstraint {
+        constraint: String,
+        source: semver::Error,
+    },
+    #[error("{message}")]
+    ArchiveCorrupt {
+        message: String,
+        source: zip::result::ZipError,
+    },
+    #[error("received {received} of {expected} bytes from {url}")]
+    Truncated {
+        url: String,
+        expected: u64,
+        received: u64,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum InstallMode {
+    /// Replace the binary at the install location
+    #[default]
+    Overwrite,
+    /// Install next to the binary location with the version appended to the file name
+    Versioned,
+    /// Install as versioned and point a symlink at the binary location to it
+    Symlink,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+enum ListFormat {
+    /// One version per line
+    #[default]
+    Plain,
+    /// Aligned columns with channel and cache status
+    Table,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum, Serialize, Deserialize)]
+enum TlsVersion {
+    #[value(name = "1.2")]
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[value(name = "1.3")]
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+impl TlsVersion {
+    fn to_reqwest_version(self) -> reqwest::tls::Version {
+        match self {
+            TlsVersion::Tls12 => reqwest::tls::Version::TLS_1_2,
+            TlsVersion::Tls13 => reqwest::tls::Version::TLS_1_3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Libc {
+    Gnu,
+    Musl,
+}
+
+impl Libc {
+    /// Detects musl-based Linux distributions like Alpine for a Linux target, assuming glibc
+    /// otherwise. Only the host can be probed, so targets for another OS always get glibc.
+    fn detect(os: &str) -> Libc {
+        if os != "linux" || consts::OS != "linux" {
+            return Libc::Gnu;
+        }
+
+        static HOST_LIBC: OnceLock<Libc> = OnceLock::new();
+        *HOST_LIBC.get_or_init(|| {
+            if Path::new("/etc/alpine-release").exists() {
+                return Libc::Musl;
+            }
+
+            // musl's ldd prints its banner to stderr and exits with an error
+            let is_musl = Command::new("ldd")
+                .arg("--version")
+                .output()
+                .is_ok_and(|output| {
+                    is_musl_ldd_output(&String::from_utf8_lossy(&output.stdout))
+                        || is_musl_ldd_output(&String::from_utf8_lossy(&output.stderr))
+                });
+            match is_musl {
+                true => Libc::Musl,
+                false => Libc::Gnu,
+            }
+        })
+    }
+}
+
+impl fmt::Display for Libc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Libc::Gnu => write!(f, "gnu"),
+            Libc::Musl => write!(f, "musl"),
+        }
+    }
+}
+
+fn is_musl_ldd_output(output: &str) -> bool {
+    output
+        .lines()
+        .next()
+        .is_some_and(|line| line.contains("musl"))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Channel {
+    Stable,
+    Rc,
+    Beta,
+    Alpha,
+}
+
+impl Channel {
+    /// Classifies a release by its pre-release tag, e.g. `rc1` or `beta2`.
+    fn of(release: &ReleaseInfo) -> Option<Channel> {
+        let pre = release.version.pre.as_str();
+        if pre.is_empty() {
+            Some(Channel::Stable)
+        } else if pre.starts_with("rc") {
+            Some(Channel::Rc)
+        } else if pre.starts_with("beta") {
+            Some(Channel::Beta)
+        } else if pre.starts_with("alpha") {
+            Some(Channel::Alpha)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum EventFormat {
+    /// One JSON object per line
+    Jsonl,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum Event<'a> {
+    Resolving,
+    Downloading { bytes: u64, total: Option<u64> },
+    Verifying { file: &'a str },
+    Extracting { path: &'a Path },
+    Done { version: &'a str },
+}
+
+impl Event<'_> {
+    fn emit(&self, format: Option<EventFormat>) {
+        if format == Some(EventFormat::Jsonl) {
+            if let std::result::Result::Ok(line) = serde_json::to_string(self) {
+                eprintln!("{line}");
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable messages
+    #[default]
+    Text,
+    /// JSON objects for scripts
+    Json,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 enum ProgramName {
     Terraform,
     OpenTofu,
 }
 
+impl ProgramName {
+    fn get_asdf_plugin_name(&self) -> &str {
+        match self {
+            ProgramName::Terraform => "terraform",
+            ProgramName::OpenTofu => "opentofu",
+        }
+    }
+
+    /// Returns the variable tfenv (or tofuenv for OpenTofu) reads the version to use from.
+    fn get_tfenv_variable_name(&self) -> &'static str {
+        match self {
+            ProgramName::Terraform => "TFENV_TERRAFORM_VERSION",
+            ProgramName::OpenTofu => "TOFUENV_TOFU_VERSION",
+        }
+    }
+
+    fn get_env_file_key(&self) -> &'static str {
+        match self {
+            ProgramName::Terraform => "TERRAFORM_VERSION",
+            ProgramName::OpenTofu => "TOFU_VERSION",
+        }
+    }
+
+    fn get_changelog_url(&self, version: &str) -> String {
+        let repository = match self {
+            ProgramName::Terraform => "hashicorp/terraform",
+            ProgramName::OpenTofu => "opentofu/opentofu",
+        };
+        format!("https://github.com/{repository}/releases/tag/v{version}")
+    }
+}
+
 impl fmt::Display for ProgramName {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -74,72 +773,291 @@ impl Args {
         }
         ProgramName::Terraform
     }
+
+    fn get_entry_name(&self) -> String {
+        match &self.entry_name {
+            Some(name) => name.clone(),
+            None => self.get_program_name().to_string(),
+        }
+    }
+
+    fn get_version_list(&self) -> VersionList {
+        if self.opentofu {
+            return VersionList::OpenTofu;
+        }
+        VersionList::Terraform
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 struct ReleaseInfo {
     program_name: ProgramName,
-    version: String,
+    version: Version,
+    /// The version exactly as published upstream, used to reconstruct download URLs
+    raw_version: String,
+    /// Publication timestamp, only known for OpenTofu or when requested with --show-dates
+    release_date: Option<String>,
+}
+
+/// Accepts versions written like GitHub tags, e.g. `v1.5.7`.
+fn strip_version_prefix(version: &str) -> &str {
+    match version.strip_prefix('v') {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => rest,
+        _ => version,
+    }
 }
 
 impl ReleaseInfo {
-    fn new(program_name: ProgramName, version: String) -> ReleaseInfo {
-        ReleaseInfo {
+    fn new(program_name: ProgramName, raw_version: String) -> Result<ReleaseInfo> {
+        let raw_version = strip_version_prefix(&raw_version).to_owned();
+        let version = Version::from_str(&raw_version)
+            .with_context(|| format!("failed to parse version {raw_version}"))?;
+        Ok(ReleaseInfo {
             program_name,
             version,
-        }
+            raw_version,
+            release_date: None,
+        })
+    }
+
+    fn is_prerelease(&self) -> bool {
+        !self.version.pre.is_empty()
+    }
+
+    fn get_zip_name(&self, target: &Target) -> String {
+        // The C library is detected when building the target if the template asks for it
+        let libc = target.libc.map(|libc| libc.to_string()).unwrap_or_default();
+        expand_template(
+            &target.archive_name_template,
+            &[
+                ("product", &self.program_name.to_string()),
+                ("version", &self.raw_version),
+                ("os", &target.os),
+                ("arch", &target.arch),
+                ("libc", &libc),
+            ],
+        )
     }
 
-    fn get_zip_name(&self) -> String {
-        let target = get_target_platform();
-        format!("{}_{}_{target}.zip", self.program_name, self.version)
+    fn get_checksums_name(&self) -> String {
+        format!("{}_{}_SHA256SUMS", self.program_name, self.raw_version)
     }
 
-    fn get_download_url(&self) -> String {
-        let zip_name = self.get_zip_name();
+    fn get_download_url(&self, target: &Target) -> String {
+        self.get_release_file_url(target, &self.get_zip_name(target))
+    }
+
+    fn get_checksums_url(&self, target: &Target) -> String {
+        self.get_release_file_url(target, &self.get_checksums_name())
+    }
+
+    /// Mirrors are expected to use the same layout as the upstream release hosting.
+    fn get_release_file_url(&self, target: &Target, file_name: &str) -> String {
+        let mirror_url = target
+            .mirror_url
+            .as_deref()
+            .map(|url| url.trim_end_matches('/'));
         match self.program_name {
-            ProgramName::Terraform => {
-                format!("{TERRAFORM_ARCHIVE_URL}/{}/{zip_name}", self.version)
-            }
-            ProgramName::OpenTofu => format!("{OPENTOFU_ARCHIVE_URL}/v{}/{zip_name}", self.version),
+            ProgramName::Terraform => format!(
+                "{}/{}/{file_name}",
+                mirror_url.unwrap_or(TERRAFORM_ARCHIVE_URL),
+                self.raw_version
+            ),
+            ProgramName::OpenTofu => format!(
+                "{}/v{}/{file_name}",
+                mirror_url.unwrap_or(OPENTOFU_ARCHIVE_URL),
+                self.raw_version
+            ),
         }
     }
 }
 
-trait ListVersions {
-    fn get_versions(&self) -> Vec<String>;
+/// The platform and archive naming used to locate release archives
+#[derive(Clone, Debug, PartialEq)]
+struct Target {
+    os: String,
+    arch: String,
+    archive_name_template: String,
+    mirror_url: Option<String>,
+    libc: Option<Libc>,
 }
 
-impl ListVersions for Vec<ReleaseInfo> {
-    fn get_versions(&self) -> Vec<String> {
-        self.iter().map(|r| r.version.to_owned()).collect()
+impl Target {
+    fn from_args(args: &Args) -> Target {
+        let host = Target::default();
+        let os = args
+            .os
+            .as_deref()
+            .map_or(host.os, |os| get_os(os).to_owned());
+        let archive_name_template = args
+            .archive_name_template
+            .clone()
+            .unwrap_or(DEFAULT_ARCHIVE_NAME_TEMPLATE.to_owned());
+        // Only probe the C library when the template asks for it
+        let libc = args.libc.or_else(|| {
+            archive_name_template
+                .contains("{libc}")
+                .then(|| Libc::detect(&os))
+        });
+        Target {
+            os,
+            arch: args
+                .arch
+                .as_deref()
+                .map_or(host.arch, |arch| get_arch(arch).to_owned()),
+            archive_name_template,
+            mirror_url: args.mirror_url.first().cloned(),
+            libc,
+        }
     }
 }
 
-enum VersionList {
-    Terraform,
-    OpenTofu,
-}
-
-impl VersionList {
-    async fn get_versions(&self, args: &Args) -> Result<Vec<ReleaseInfo>> {
-        match self {
-            VersionList::Terraform => Ok(get_versions_terraform(args).await?),
-            VersionList::OpenTofu => Ok(get_versions_opentofu(args).await?),
+impl Default for Target {
+    fn default() -> Target {
+        Target {
+            os: get_os(consts::OS).to_owned(),
+            arch: get_arch(consts::ARCH).to_owned(),
+            archive_name_template: DEFAULT_ARCHIVE_NAME_TEMPLATE.to_owned(),
+            mirror_url: None,
+            libc: None,
         }
     }
 }
 
-async fn get_versions_terraform(args: &Args) -> Result<Vec<ReleaseInfo>> {
-    let response = get_http(TERRAFORM_ARCHIVE_URL).await?;
-    let contents = response
-        .text()
-        .await
-        .with_context(|| "failed to get Terraform versions")?;
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.os, self.arch)
+    }
+}
+
+fn expand_template(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_owned(), |expanded, (key, value)| {
+            expanded.replace(&format!("{{{key}}}"), value)
+        })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Checksum {
+    Sha256([u8; 32]),
+    Sha512([u8; 64]),
+}
+
+impl Checksum {
+    /// Parses a hex-encoded digest, picking the algorithm from the digest length.
+    fn from_hex(hex: &str) -> Result<Checksum> {
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                hex.get(i..i + 2)
+                    .and_then(|b| u8::from_str_radix(b, 16).ok())
+            })
+            .collect::<Option<Vec<u8>>>()
+            .with_context(|| format!("invalid checksum {hex}"))?;
+
+        match bytes.len() {
+            32 => Ok(Checksum::Sha256(
+                bytes.try_into().expect("Invalid digest length"),
+            )),
+            64 => Ok(Checksum::Sha512(
+                bytes.try_into().expect("Invalid digest length"),
+            )),
+            _ => bail!("unsupported checksum length for {hex}"),
+        }
+    }
+
+    fn verify(&self, buffer: &[u8]) -> bool {
+        match self {
+            Checksum::Sha256(expected) => Sha256::digest(buffer).as_slice() == expected,
+            Checksum::Sha512(expected) => Sha512::digest(buffer).as_slice() == expected,
+        }
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digest = match self {
+            Checksum::Sha256(digest) => digest.as_slice(),
+            Checksum::Sha512(digest) => digest.as_slice(),
+        };
+        digest.iter().try_for_each(|b| write!(f, "{b:02x}"))
+    }
+}
+
+trait ListVersions {
+    fn get_versions(&self) -> Vec<String>;
+}
+
+impl ListVersions for [ReleaseInfo] {
+    fn get_versions(&self) -> Vec<String> {
+        self.iter().map(|r| r.raw_version.to_owned()).collect()
+    }
+}
+
+enum VersionList {
+    Terraform,
+    OpenTofu,
+}
+
+impl VersionList {
+    async fn get_versions(&self, client: &Client, args: &Args) -> Result<Vec<ReleaseInfo>> {
+        let mut versions = match self {
+            VersionList::Terraform => get_versions_terraform(client, args).await?,
+            VersionList::OpenTofu => get_versions_opentofu(client, args).await?,
+        };
+        sort_versions(&mut versions);
+        if let Some(constraint) = &args.policy_constraint {
+            versions = filter_versions_by_constraint(versions, constraint)?;
+        }
+
+        // The releases API is paged, so it is only read when the dates are used. Withdrawn
+        // releases are otherwise skipped when a candidate is picked, see `pick_available`
+        let wants_dates = args.show_dates
+            || args.newer_than.is_some()
+            || (args.list && args.output == OutputFormat::Json);
+        if wants_dates && matches!(self, VersionList::Terraform) {
+            if let Err(e) =
+                add_terraform_release_metadata(client, &mut versions, &args.retry_on).await
+            {
+                if args.newer_than.is_some() {
+                    return Err(e.context("release dates are needed for --newer-than"));
+                }
+                status!("Unable to get release dates: {e}");
+            }
+        }
+        if let Some(days) = args.newer_than {
+            versions = filter_versions_newer_than(versions, days, get_days_since_epoch())?;
+        }
+
+        Ok(versions)
+    }
+}
+
+/// Sorts versions from newest to oldest.
+fn sort_versions(versions: &mut [ReleaseInfo]) {
+    versions.sort_by(|a, b| b.version.cmp(&a.version));
+}
+
+async fn get_versions_terraform(client: &Client, args: &Args) -> Result<Vec<ReleaseInfo>> {
+    let response = get_http(client, TERRAFORM_ARCHIVE_URL, &args.retry_on).await?;
+    let contents = response
+        .text()
+        .await
+        .with_context(|| "failed to get Terraform versions")?;
+    if !is_complete_html_index(&contents) {
+        status!("Warning: the Terraform releases index looks truncated, so some versions may be missing");
+    }
 
     Ok(capture_terraform_versions(args, &contents))
 }
 
+/// A partially served releases index is cut off before its closing tags.
+fn is_complete_html_index(contents: &str) -> bool {
+    let tail = contents.trim_end().to_ascii_lowercase();
+    tail.contains("</ul>") && tail.ends_with("</html>")
+}
+
 fn capture_terraform_versions(args: &Args, contents: &str) -> Vec<ReleaseInfo> {
     let re = if args.list_all {
         Regex::new(r"terraform_(?<version>(\d+\.\d+\.\d+)(?:-[a-zA-Z0-9-]+)?)")
@@ -152,347 +1070,4020 @@ fn capture_terraform_versions(args: &Args, contents: &str) -> Vec<ReleaseInfo> {
         .captures_iter(contents)
         .filter_map(|c| {
             c.name("version")
-                .map(|v| ReleaseInfo::new(args.get_program_name(), v.as_str().to_owned()))
+                .and_then(|v| ReleaseInfo::new(args.get_program_name(), v.as_str().to_owned()).ok())
         })
         .collect();
 
     versions
 }
 
-async fn get_versions_opentofu(args: &Args) -> Result<Vec<ReleaseInfo>> {
-    let releases = octocrab::instance()
-        .repos("opentofu", "opentofu")
-        .releases()
-        .list()
-        .send()
-        .await
-        .with_context(|| "failed to get releases from opentofu github repo")?;
-
-    let versions = releases
-        .into_iter()
-        .filter(|r| !r.prerelease || args.list_all)
-        .map(|r| {
-            let version = match r.tag_name.strip_prefix('v') {
-                Some(v) => v.to_owned(),
-                None => r.tag_name.clone(),
-            };
-            ReleaseInfo::new(args.get_program_name(), version)
-        })
-        .collect();
+#[derive(Deserialize)]
+struct GitHubRelease {
+    tag_name: String,
+    prerelease: bool,
+    published_at: Option<String>,
+}
 
-    Ok(versions)
+#[derive(Deserialize, Debug)]
+struct HashiCorpRelease {
+    version: String,
+    timestamp_created: String,
+    #[serde(default)]
+    status: Option<HashiCorpReleaseStatus>,
 }
 
-async fn get_http(url: &str) -> Result<Response> {
-    let response = reqwest::get(url)
-        .await
-        .with_context(|| format!("failed to send HTTP request to {url}"))?
-        .error_for_status()
-        .with_context(|| format!("server returned error from {url}"))?;
+#[derive(Deserialize, Debug)]
+struct HashiCorpReleaseStatus {
+    state: String,
+}
 
-    Ok(response)
+impl HashiCorpRelease {
+    /// HashiCorp withdraws releases that shipped with a known defect.
+    fn is_withdrawn(&self) -> bool {
+        self.status.as_ref().is_some_and(|s| s.state == "withdrawn")
+    }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let mut args = Args::parse();
-    parse_config_arguments(".".into(), &mut args)?;
+async fn is_release_withdrawn(
+    client: &Client,
+    release: &ReleaseInfo,
+    retry_on: &[u16],
+) -> Result<bool> {
+    let url = format!("{TERRAFORM_RELEASES_URL}/{}", release.raw_version);
+    let metadata: HashiCorpRelease = get_http(client, &url, retry_on)
+        .await?
+        .json()
+        .await
+        .with_context(|| format!("failed to get Terraform release {}", release.raw_version))?;
+    Ok(metadata.is_withdrawn())
+}
 
-    if let Some(generator) = args.generator {
-        clap_complete::generate(
-            generator,
-            &mut Args::command_for_update(),
-            clap::crate_name!(),
-            &mut io::stdout(),
+async fn warn_if_withdrawn(client: &Client, release: &ReleaseInfo, retry_on: &[u16]) -> Result<()> {
+    if is_release_withdrawn(client, release, retry_on).await? {
+        status!(
+            "Warning: Terraform {} has been withdrawn by HashiCorp, consider another version",
+            release.raw_version
         );
-        return Ok(());
     }
 
-    let Some(program_path) = find_terraform_program_path(&args) else {
-        bail!(format!(
-            "could not find path to install {:?}",
-            args.get_program_name()
-        ));
-    };
-
-    match get_version_to_install(&args).await? {
-        Some(version) => Ok(install_version(&args, &program_path, version).await?),
-        None => bail!("no version to install"),
-    }
+    Ok(())
 }
 
-fn parse_config_arguments(cwd: PathBuf, args: &mut Args) -> Result<()> {
-    if let Some(config) = load_config_file(cwd, home::home_dir())? {
-        if args.binary_location.is_none() {
-            args.binary_location = config.binary_location
-        }
-        args.list_all |= config.list_all;
-        args.opentofu |= config.opentofu;
-        if args.install_version.is_none() {
-            args.install_version = config.install_version
-        }
-    }
+async fn get_versions_opentofu(client: &Client, args: &Args) -> Result<Vec<ReleaseInfo>> {
+    let releases: Vec<GitHubRelease> = get_http(client, OPENTOFU_RELEASES_URL, &args.retry_on)
+        .await?
+        .json()
+        .await
+        .with_context(|| "failed to get releases from opentofu github repo")?;
 
-    Ok(())
+    let versions = releases
+        .into_iter()
+        .filter(|r| !r.prerelease || args.list_all)
+        .filter_map(|r| {
+            let mut release = ReleaseInfo::new(args.get_program_name(), r.tag_name).ok()?;
+            release.release_date = r.published_at;
+            Some(release)
+        })
+        .collect();
+
+    Ok(versions)
 }
 
-fn load_config_file(mut cwd: PathBuf, mut home_dir: Option<PathBuf>) -> Result<Option<Args>> {
-    cwd.push(CONFIG_FILE_NAME);
-    if cwd.exists() {
-        let config = fs::read_to_string(&cwd)
-            .with_context(|| format!("failed to read config file in cwd at {:?}", cwd))?;
-        let toml_file = toml::from_str(&config)
-            .with_context(|| format!("failed to parse config in cwd at {cwd:?}"))?;
-        return Ok(Some(toml_file));
-    }
+/// Fills in release dates from the HashiCorp releases API, which is paged in creation order, and
+/// drops withdrawn releases.
+async fn add_terraform_release_metadata(
+    client: &Client,
+    versions: &mut Vec<ReleaseInfo>,
+    retry_on: &[u16],
+) -> Result<()> {
+    let mut dates = HashMap::new();
+    let mut withdrawn = Vec::new();
+    let mut after: Option<String> = None;
+    while versions.iter().any(|r| !dates.contains_key(&r.raw_version)) {
+        let url = match &after {
+            Some(after) => format!("{TERRAFORM_RELEASES_URL}?limit=20&after={after}"),
+            None => format!("{TERRAFORM_RELEASES_URL}?limit=20"),
+        };
+        let page: Vec<HashiCorpRelease> = get_http(client, &url, retry_on)
+            .await?
+            .json()
+            .await
+            .with_context(|| "failed to get Terraform releases")?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        if after.as_ref() == Some(&last.timestamp_created) {
+            break;
+        }
 
-    match home_dir.as_mut() {
-        Some(home) => {
-            home.push(CONFIG_FILE_NAME);
-            if !home.exists() {
-                return Ok(None);
+        after = Some(last.timestamp_created.clone());
+        for release in page {
+            if release.is_withdrawn() {
+                withdrawn.push(release.version.clone());
             }
-
-            let config = fs::read_to_string(&home)
-                .with_context(|| format!("failed to read config file in home at {home:?}"))?;
-            let toml_file = toml::from_str(&config)
-                .with_context(|| format!("failed to parse config in home at {home:?}"))?;
-            Ok(Some(toml_file))
+            dates.insert(release.version, release.timestamp_created);
         }
-        None => Ok(None),
     }
-}
 
-fn find_terraform_program_path(args: &Args) -> Option<PathBuf> {
-    if args.binary_location.is_some() {
-        return args.binary_location.clone();
+    versions.retain(|r| !withdrawn.contains(&r.raw_version));
+    for release in versions {
+        release.release_date = dates.remove(&release.raw_version);
     }
 
-    let program_name = args.get_program_name();
+    Ok(())
+}
 
-    if let Some(path) = pathsearch::find_executable_in_path(&program_name.to_string()) {
-        return Some(path);
+fn build_http_client(args: &Args) -> Result<Client> {
+    let mut builder =
+        Client::builder().user_agent(args.user_agent.as_deref().unwrap_or(USER_AGENT));
+
+    // Binding to an unspecified local address restricts connections to that address family
+    match (args.ipv4, args.ipv6) {
+        (true, true) => bail!("cannot restrict connections to both IPv4 and IPv6"),
+        (true, false) => builder = builder.local_address(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+        (false, true) => builder = builder.local_address(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+        (false, false) => {}
     }
 
-    match home::home_dir() {
-        Some(mut path) => {
-            path.push(format!("{DEFAULT_LOCATION}/{program_name}"));
-            println!(
-                "Could not locate {program_name:?}, installing to {path:?}\nMake sure to include the directory in your $PATH environment variable"
-            );
-            Some(path)
-        }
-        None => None,
+    if let Some(version) = args.min_tls {
+        builder = builder.min_tls_version(version.to_reqwest_version());
     }
-}
 
-async fn get_version_to_install(args: &Args) -> Result<Option<ReleaseInfo>> {
-    if let Some(version) = &args.install_version {
-        return Ok(Some(ReleaseInfo::new(
-            args.get_program_name(),
-            version.into(),
-        )));
+    for path in &args.ca_cert {
+        builder = builder.add_root_certificate(load_ca_certificate(path)?);
     }
 
-    let version_list = if args.opentofu {
-        VersionList::OpenTofu
-    } else {
-        VersionList::Terraform
-    };
-    let versions = version_list.get_versions(args).await?;
+    if args.insecure_skip_tls_verify {
+        eprintln!(
+            "WARNING: TLS certificate verification is disabled, downloads can be tampered with"
+        );
+        builder = builder.danger_accept_invalid_certs(true);
+    }
 
-    if let Some(version_from_module) = get_version_from_module(Path::new("."), &versions)? {
-        return Ok(Some(version_from_module));
+    if let Some(socket_path) = &args.unix_proxy {
+        let (addr, token) = start_unix_proxy_bridge(socket_path)?;
+        let proxy = reqwest::Proxy::all(format!("http://{addr}"))
+            .with_context(|| "failed to configure Unix socket proxy")?
+            .basic_auth(UNIX_PROXY_USER, &token);
+        builder = builder.proxy(proxy);
     }
 
-    get_version_from_user_prompt(args.get_program_name(), &versions)
+    builder
+        .build()
+        .with_context(|| "failed to build HTTP client")
 }
 
-fn get_version_from_module(cwd: &Path, versions: &Vec<ReleaseInfo>) -> Result<Option<ReleaseInfo>> {
-    let module =
-        tfconfig::load_module(cwd, false).with_context(|| "failed to load terraform modules")?;
-    let version_constraint = match module.required_core.first() {
-        Some(version) => version,
-        None => return Ok(None),
-    };
+fn load_ca_certificate(path: &Path) -> Result<reqwest::Certificate> {
+    let contents =
+        fs::read(path).with_context(|| format!("failed to read CA certificate {path:?}"))?;
+    reqwest::Certificate::from_pem(&contents)
+        .with_context(|| format!("failed to parse CA certificate {path:?} as PEM"))
+}
 
-    println!("Module constraint is {version_constraint}");
+/// reqwest can't connect to a proxy over a Unix domain socket, so listen on a loopback port and
+/// forward each connection to the socket, letting reqwest treat it as a regular HTTP proxy. Any
+/// local user can reach the port, so only connections carrying the returned random token as
+/// proxy credentials are forwarded.
+#[cfg(unix)]
+fn start_unix_proxy_bridge(socket_path: &Path) -> Result<(SocketAddr, String)> {
+    let mut token = [0; 16];
+    File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(&mut token))
+        .with_context(|| "failed to generate Unix socket proxy token")?;
+    let token: String = token.iter().map(|b| format!("{b:02x}")).collect();
 
-    let req = VersionReq::parse(version_constraint)
-        .with_context(|| format!("failed to parse version constraint {version_constraint}"))?;
-    for version in versions {
-        let v = Version::from_str(&version.version)
-            .with_context(|| format!("failed to parse version {}", version.version))?;
-        if req.matches(&v) {
-            return Ok(Some(version.clone()));
-        }
-    }
+    let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .with_context(|| "failed to listen for Unix socket proxy connections")?;
+    listener.set_nonblocking(true)?;
+    let addr = listener.local_addr()?;
+    let listener = tokio::net::TcpListener::from_std(listener)?;
 
-    Ok(None)
+    let credentials = get_proxy_credentials(&token);
+    tokio::spawn(run_unix_proxy_bridge(
+        listener,
+        socket_path.to_path_buf(),
+        credentials,
+    ));
+
+    Ok((addr, token))
 }
 
-fn get_version_from_user_prompt(
-    program_name: ProgramName,
-    versions: &Vec<ReleaseInfo>,
-) -> Result<Option<ReleaseInfo>> {
-    match Select::with_theme(&ColorfulTheme::default())
-        .with_prompt(format!("Select a {program_name:?} version to install"))
-        .items(&versions.get_versions())
-        .default(0)
-        .interact_opt()
-        .with_context(|| "failed to get version from user prompt")?
-    {
-        Some(selection) => Ok(versions.get(selection).cloned()),
-        None => Ok(None),
+#[cfg(unix)]
+async fn run_unix_proxy_bridge(
+    listener: tokio::net::TcpListener,
+    socket_path: PathBuf,
+    credentials: String,
+) -> Result<()> {
+    loop {
+        let (inbound, _) = listener.accept().await?;
+        let socket_path = socket_path.clone();
+        let credentials = credentials.clone();
+        tokio::spawn(async move {
+            if let Err(e) = forward_to_unix_socket(inbound, &socket_path, &credentials).await {
+                status!("Unable to forward request to proxy at {socket_path:?}: {e}");
+            }
+        });
     }
 }
 
-async fn install_version(args: &Args, program_path: &Path, release: ReleaseInfo) -> Result<()> {
-    println!(
-        "{:?} {} will be installed to {program_path:?}",
-        args.get_program_name(),
-        release.version
-    );
+#[cfg(unix)]
+async fn forward_to_unix_socket(
+    mut inbound: tokio::net::TcpStream,
+    socket_path: &Path,
+    credentials: &str,
+) -> Result<()> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-    let archive = get_zip(&release).await?;
-    extract_zip_archive(args.get_program_name(), program_path, archive)
-}
+    let mut buffer = Vec::new();
+    let head_len = loop {
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buffer.len() > MAX_PROXY_REQUEST_HEAD {
+            bail!("proxy request head is too large");
+        }
+        let mut chunk = [0; 4096];
+        let len = inbound.read(&mut chunk).await?;
+        if len == 0 {
+            bail!("proxy connection closed before the request head");
+        }
+        buffer.extend_from_slice(&chunk[..len]);
+    };
 
-async fn get_zip(release: &ReleaseInfo) -> Result<ZipArchive<Cursor<Vec<u8>>>> {
-    if let Some(cursor) = get_cached_zip(home::home_dir().as_mut(), &release.get_zip_name())? {
-        let archive = ZipArchive::new(cursor).with_context(|| "failed to read cached archive")?;
-        return Ok(archive);
-    }
+    let Some(mut request) = strip_proxy_credentials(&buffer[..head_len], credentials) else {
+        inbound
+            .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+            .await?;
+        bail!("rejected a connection without the proxy token");
+    };
+    request.extend_from_slice(&buffer[head_len..]);
 
-    download_and_save_zip(release).await
+    let mut outbound = tokio::net::UnixStream::connect(socket_path)
+        .await
+        .with_context(|| format!("failed to connect to {socket_path:?}"))?;
+    outbound.write_all(&request).await?;
+    tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await?;
+
+    Ok(())
 }
 
-fn get_target_platform() -> String {
-    let os = consts::OS;
-    let arch = get_arch(consts::ARCH);
+/// The `Proxy-Authorization` value reqwest sends for the bridge token.
+#[cfg(unix)]
+fn get_proxy_credentials(token: &str) -> String {
+    use base64::Engine;
 
-    format!("{os}_{arch}")
+    let encoded =
+        base64::engine::general_purpose::STANDARD.encode(format!("{UNIX_PROXY_USER}:{token}"));
+    format!("Basic {encoded}")
 }
 
-fn get_arch(arch: &str) -> &str {
-    match arch {
-        "x86" => "386",
-        "x86_64" => "amd64",
-        "aarch64" => "arm64",
-        _ => arch,
-    }
+/// Removes the `Proxy-Authorization` header from a request head if it carries `credentials`, so
+/// that the bridge token is not passed on to the proxy. Returns `None` if it is missing or wrong.
+#[cfg(unix)]
+fn strip_proxy_credentials(head: &[u8], credentials: &str) -> Option<Vec<u8>> {
+    let head = std::str::from_utf8(head).ok()?;
+    let mut found = false;
+    let lines: Vec<&str> = head
+        .split("\r\n")
+        .filter(|line| {
+            let is_token = line.split_once(':').is_some_and(|(name, value)| {
+                name.trim().eq_ignore_ascii_case("proxy-authorization")
+                    && value.trim() == credentials
+            });
+            found |= is_token;
+            !is_token
+        })
+        .collect();
+
+    found.then(|| lines.join("\r\n").into_bytes())
 }
 
-fn get_cached_zip(
-    home_dir: Option<&mut PathBuf>,
-    zip_name: &str,
-) -> Result<Option<Cursor<Vec<u8>>>> {
-    match home_dir {
-        Some(path) => {
-            path.push(format!("{DEFAULT_CACHE_LOCATION}/{zip_name}"));
-            if !path.exists() {
-                return Ok(None);
-            }
+#[cfg(windows)]
+fn start_unix_proxy_bridge(_socket_path: &Path) -> Result<(SocketAddr, String)> {
+    bail!("Unix socket proxies are not supported on Windows")
+}
 
-            println!("Using cached archive at {path:?}");
-            let buffer = fs::read(&path)
-                .with_context(|| format!("failed to read cached archive at {path:?}"))?;
-            let cursor = Cursor::new(buffer);
+/// Times a request to each mirror and returns the one that responded fastest. Any response counts,
+/// as the base URL of a mirror does not have to serve anything itself.
+async fn pick_fastest_mirror(client: &Client, mirror_urls: &[String]) -> Result<String> {
+    let mut fastest: Option<(&String, Duration)> = None;
+    for mirror_url in mirror_urls {
+        let start = Instant::now();
+        if let Err(e) = client.head(mirror_url).send().await {
+            status!("Mirror {mirror_url} is unreachable: {e}");
+            continue;
+        }
 
-            Ok(Some(cursor))
+        let latency = start.elapsed();
+        status!("Mirror {mirror_url} responded in {}ms", latency.as_millis());
+        if fastest.map_or(true, |(_, best)| latency < best) {
+            fastest = Some((mirror_url, latency));
         }
-        None => Ok(None),
     }
-}
-
-async fn download_and_save_zip(release: &ReleaseInfo) -> Result<ZipArchive<Cursor<Vec<u8>>>> {
-    let url = release.get_download_url();
-    println!("Downloading archive from {url}");
-    let response = get_http(&url).await?;
-    let contents = response
-        .bytes()
-        .await
-        .with_context(|| "failed to read HTTP response")?
-        .to_vec();
 
-    match home::home_dir() {
-        Some(mut path) => {
-            path.push(DEFAULT_CACHE_LOCATION);
-            println!("Caching archive to {path:?}");
-            if let Err(e) = cache_zip_archive(&mut path, &release.get_zip_name(), &contents) {
-                println!("Unable to cache archive: {e}");
-            };
+    match fastest {
+        Some((mirror_url, latency)) => {
+            status!("Using mirror {mirror_url} ({}ms)", latency.as_millis());
+            Ok(mirror_url.clone())
         }
-        None => println!("Unable to cache archive: could not find home directory"),
+        None => bail!("none of the mirrors are reachable"),
     }
-
-    let cursor = Cursor::new(contents);
-    Ok(ZipArchive::new(cursor).with_context(|| "failed to read HTTP response as ZIP archive")?)
 }
 
-fn cache_zip_archive(cache_location: &mut PathBuf, zip_name: &str, buffer: &[u8]) -> Result<()> {
-    fs::create_dir_all(&cache_location)?;
-    cache_location.push(zip_name);
-    fs::write(cache_location, buffer)?;
-
-    Ok(())
+/// Whether a URL is plain HTTP, compared case-insensitively like the scheme itself.
+fn is_unencrypted(url: &str) -> bool {
+    url.get(..7)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("http://"))
 }
 
-fn extract_zip_archive(
-    program_name: ProgramName,
-    program_path: &Path,
-    mut archive: ZipArchive<Cursor<Vec<u8>>>,
-) -> Result<()> {
-    let mut file = archive
-        .by_name(&program_name.to_string())
-        .with_context(|| "could not get item in archive")?;
-    let file_name = file.name();
-    println!("Extracting {file_name} to {program_path:?}");
+async fn get_http(client: &Client, url: &str, retry_on: &[u16]) -> Result<Response> {
+    let mut attempt = 1;
+    loop {
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .map_err(|source| TfswitcherError::Http {
+                message: format!("failed to send HTTP request to {url}"),
+                source,
+            })?;
 
-    // Create a new file for the extracted file and set rwxr-xr-x
-    let mut outfile = create_output_file(program_path)?;
+        let status = response.status();
+        if is_retryable_status(status, retry_on) && attempt < MAX_HTTP_ATTEMPTS {
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok());
+            let delay = get_retry_delay(retry_after, attempt);
+            match status {
+                reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                    status!("Rate limited by {url}, retrying in {}s", delay.as_secs())
+                }
+                _ => status!("{url} returned {status}, retrying in {}s", delay.as_secs()),
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+            continue;
+        }
 
-    // Write the contents of the file to the output file
-    io::copy(&mut file, &mut outfile).with_context(|| "failed to extract zip archive")?;
+        let response = response
+            .error_for_status()
+            .map_err(|source| TfswitcherError::Http {
+                message: format!("server returned error from {url}"),
+                source,
+            })?;
+        return Ok(response);
+    }
+}
 
-    println!("Extracted archive to {program_path:?}");
-    Ok(())
+/// Rate-limited requests are always retried, as are the status codes given with --retry-on.
+fn is_retryable_status(status: reqwest::StatusCode, retry_on: &[u16]) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || retry_on.contains(&status.as_u16())
 }
 
-#[cfg(unix)]
-fn create_output_file(program_path: &Path) -> Result<File> {
-    let file = File::create(program_path)
-        .with_context(|| format!("failed to create file at {program_path:?}"))?;
-    let mut perms = file
-        .metadata()
-        .with_context(|| "could not get file metadata")?
-        .permissions();
-    perms.set_mode(0o755);
-    file.set_permissions(perms)
-        .with_context(|| "could not set file permissions")?;
+/// Returns how long to wait before retrying a rate-limited request. `Retry-After` is honoured
+/// when given in seconds, otherwise the delay doubles with each attempt.
+fn get_retry_delay(retry_after: Option<&str>, attempt: u32) -> Duration {
+    let delay = retry_after
+        .and_then(|value| value.trim().parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or_else(|| Duration::from_secs(1 << attempt));
+    delay.min(MAX_RETRY_DELAY)
+}
 
-    Ok(file)
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let output = args.output;
+    match run(args).await {
+        Err(e) if output == OutputFormat::Json => {
+            eprintln!("{}", format_error_json(&e));
+            process::exit(1);
+        }
+        result => result,
+    }
 }
 
-#[cfg(windows)]
-fn create_output_file(program_path: &Path) -> Result<File> {
-    Ok(File::create(program_path)
-        .with_context(|| format!("failed to create file at {program_path:?}"))?)
+fn format_error_json(error: &anyhow::Error) -> String {
+    serde_json::json!({
+        "error": format!("{error:#}"),
+        "kind": get_error_kind(error),
+    })
+    .to_string()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use html_to_string_macro::html;
-    use once_cell::sync::Lazy;
-    use tempdir::TempDir;
+fn get_error_kind(error: &anyhow::Error) -> &'static str {
+    match error.downcast_ref() {
+        Some(TfswitcherError::Http { .. }) => "http",
+        Some(TfswitcherError::VersionNotFound(_)) => "version_not_found",
+        Some(TfswitcherError::Io { .. }) => "io",
+        Some(TfswitcherError::InvalidConstraint { .. }) => "invalid_constraint",
+        Some(TfswitcherError::ArchiveCorrupt { .. }) => "archive_corrupt",
+        Some(TfswitcherError::Truncated { .. }) => "truncated",
+        None => "other",
+    }
+}
 
-    static LINES: Lazy<String> = Lazy::new(|| {
-        html!(
-        <html>
-            <head>
-                <title>"Terraform Versions | HashiCorp Releases"</title>
-            </head>
+/// Modes whose stdout is read by scripts, or left to the binary when wrapping it, so that status
+/// messages have to go to stderr.
+fn is_data_output(args: &Args) -> bool {
+    args.stdout
+        || args.exec
+        || args.print_url
+        || args.download_only
+        || args.list
+        || args.output == OutputFormat::Json
+        || !args.targets.is_empty()
+        || args.resolve_constraint.is_some()
+        || args.print_checksum.is_some()
+        || args.list_os_arch.is_some()
+}
+
+async fn run(mut args: Args) -> Result<()> {
+    parse_config_arguments(".".into(), &mut args)?;
+
+    if args.stdout && io::stdout().is_terminal() {
+        bail!("refusing to write the binary to a terminal, redirect stdout to a file or pipe");
+    }
+    if is_data_output(&args) {
+        STATUS_TO_STDERR.store(true, Ordering::Relaxed);
+    }
+
+    if let Some(generator) = args.generator {
+        clap_complete::generate(
+            generator,
+            &mut Args::command_for_update(),
+            clap::crate_name!(),
+            &mut io::stdout(),
+        );
+        return Ok(());
+    }
+
+    if args.print_config {
+        print!("{}", format_config(&args)?);
+        return Ok(());
+    }
+
+    if let (Some(alias), Some(version)) = (&args.alias, &args.install_version) {
+        let Some(config_dir) = get_config_dir() else {
+            bail!("could not find config directory to save aliases in");
+        };
+        return save_alias(&config_dir, alias, version);
+    }
+
+    for dir in [get_shared_cache_dir(), get_cache_dir(&args)]
+        .into_iter()
+        .flatten()
+    {
+        remove_stale_temp_files(&dir);
+        remove_stale_temp_files(&dir.join(BINARY_CACHE_DIR_NAME));
+    }
+
+    if args.prune {
+        let Some(cache_dir) = get_cache_dir(&args) else {
+            bail!("could not find cache directory");
+        };
+        let candidates = get_prune_candidates(&cache_dir)?;
+        confirm_removal(&args, &candidates)?;
+        return remove_files(&candidates, args.dry_run);
+    }
+
+    if let Some(tar_path) = &args.export_cache {
+        let Some(cache_dir) = get_cache_dir(&args) else {
+            bail!("could not find cache directory");
+        };
+        return export_cache(&cache_dir, tar_path);
+    }
+
+    if let Some(tar_path) = &args.import_cache {
+        let Some(cache_dir) = get_cache_dir(&args) else {
+            bail!("could not find cache directory");
+        };
+        return import_cache(&args, &cache_dir, tar_path);
+    }
+
+    let client = build_http_client(&args)?;
+
+    if let Some(url) = &args.policy_url {
+        args.policy_constraint =
+            get_policy_constraint(&client, url, &args.get_program_name(), &args.retry_on).await?;
+    }
+
+    if args.check_self_update {
+        if let Err(e) = check_self_update(&client, &args.retry_on).await {
+            status!("Unable to check for tfswitcher updates: {e}");
+        }
+    }
+
+    let mut target = Target::from_args(&args);
+
+    if args.list {
+        return list_versions(&client, &args, &target).await;
+    }
+
+    if args.compare {
+        return compare_versions(&client, &args).await;
+    }
+
+    if args.pick_fastest_mirror && args.mirror_url.len() > 1 {
+        target.mirror_url = Some(pick_fastest_mirror(&client, &args.mirror_url).await?);
+    }
+    if let Some(mirror_url) = target
+        .mirror_url
+        .as_deref()
+        .filter(|url| is_unencrypted(url))
+    {
+        eprintln!("WARNING: mirror {mirror_url} is reached over unencrypted HTTP, and --verify can't detect tampering as SHA256SUMS comes from the same mirror, use --cosign for OpenTofu or --from-zip with --checksum");
+    }
+
+    if let Some(version) = &args.list_os_arch {
+        return list_platforms(&client, &args, &target, version).await;
+    }
+
+    if let Some(version) = &args.print_checksum {
+        let release = ReleaseInfo::new(args.get_program_name(), version.clone())?;
+        let archive = get_zip(&client, &args, &target, &release).await?;
+        println!(
+            "{}",
+            format_checksum_line(
+                archive.into_inner().get_ref(),
+                &release.get_zip_name(&target)
+            )
+        );
+        return Ok(());
+    }
+
+    if let Some(constraint) = &args.resolve_constraint {
+        return resolve_constraint(&client, &args, constraint).await;
+    }
+
+    if args.print_url {
+        let release = resolve_version_or_bail(&client, &args).await?;
+        println!("{}", release.get_download_url(&target));
+        return Ok(());
+    }
+
+    if let Some(batch_path) = &args.batch {
+        return install_batch(&client, &args, &target, batch_path).await;
+    }
+
+    if args.download_only {
+        let release = resolve_version_or_bail(&client, &args).await?;
+        return download_only(&client, &args, &target, &release).await;
+    }
+
+    if !args.targets.is_empty() {
+        let release = resolve_version_or_bail(&client, &args).await?;
+        return download_targets(&client, &args, &target, &release).await;
+    }
+
+    if args.stdout {
+        let release = resolve_version_or_bail(&client, &args).await?;
+        let archive = get_zip(&client, &args, &target, &release).await?;
+        return write_program_to_stdout(&args.get_entry_name(), &target.os, archive);
+    }
+
+    let Some(program_path) = find_terraform_program_path(&args) else {
+        bail!(format!(
+            "could not find path to install {:?}",
+            args.get_program_name()
+        ));
+    };
+
+    if let Some(zip_path) = &args.from_zip {
+        let installed_path = install_from_zip(&args, &program_path, zip_path)?;
+        if args.exec {
+            return exec_program(&installed_path, &args.exec_args);
+        }
+        return Ok(());
+    }
+
+    if let Some(version) = &args.uninstall {
+        let release = ReleaseInfo::new(args.get_program_name(), version.clone())?;
+        let candidates = get_uninstall_candidates(&args, &target, &program_path, &release)?;
+        if candidates.is_empty() {
+            bail!(
+                "{:?} {version} is not installed or cached",
+                release.program_name
+            );
+        }
+        confirm_removal(&args, &candidates)?;
+        return remove_files(&candidates, args.dry_run);
+    }
+
+    if args.audit {
+        return audit_installed_version(&client, &args, &target, &program_path).await;
+    }
+
+    Event::Resolving.emit(args.events);
+    let version = resolve_version_or_bail(&client, &args).await?;
+    if args.verify_only {
+        let installed = get_installed_version(&program_path)?;
+        let installed = ReleaseInfo::new(args.get_program_name(), installed)?;
+        return check_installed_version(&installed, &version);
+    }
+
+    let raw_version = version.raw_version.clone();
+    let installed_path = match args.reinstall {
+        true => reinstall_from_cache(&args, &target, &program_path, version)?,
+        false => install_version(&client, &args, &target, &program_path, version).await?,
+    };
+    Event::Done {
+        version: &raw_version,
+    }
+    .emit(args.events);
+
+    if args.exec {
+        return exec_program(&installed_path, &args.exec_args);
+    }
+    Ok(())
+}
+
+/// Replaces the process with the installed binary, so that signals and the exit code are the
+/// binary's own.
+#[cfg(unix)]
+fn exec_program(program_path: &Path, exec_args: &[String]) -> Result<()> {
+    use std::os::unix::process::CommandExt;
+
+    let error = Command::new(program_path).args(exec_args).exec();
+    Err(error).with_context(|| format!("failed to run {program_path:?}"))
+}
+
+#[cfg(not(unix))]
+fn exec_program(program_path: &Path, exec_args: &[String]) -> Result<()> {
+    let status = Command::new(program_path)
+        .args(exec_args)
+        .status()
+        .with_context(|| format!("failed to run {program_path:?}"))?;
+    process::exit(status.code().unwrap_or(1));
+}
+
+async fn list_platforms(
+    client: &Client,
+    args: &Args,
+    target: &Target,
+    version: &str,
+) -> Result<()> {
+    let release = ReleaseInfo::new(args.get_program_name(), version.into())?;
+    let contents = match get_http(client, &release.get_checksums_url(target), &args.retry_on).await
+    {
+        Err(e) if is_not_found(&e) => {
+            return Err(e.context(format!(
+                "{:?} {version} was not found",
+                release.program_name
+            )))
+        }
+        response => response?
+            .text()
+            .await
+            .with_context(|| "failed to read checksums")?,
+    };
+
+    let platforms = parse_platforms(&release, &contents);
+    if platforms.is_empty() {
+        bail!(
+            "no platforms found for {:?} {version}",
+            release.program_name
+        );
+    }
+    for platform in platforms {
+        println!("{platform}");
+    }
+
+    Ok(())
+}
+
+/// Collects the `os/arch` platforms from the archive names listed in a release's checksums file.
+fn parse_platforms(release: &ReleaseInfo, checksums: &str) -> Vec<String> {
+    let re = Regex::new(&format!(
+        r"^{}_{}_(?<os>[a-z0-9]+)_(?<arch>[a-z0-9]+)\.zip$",
+        regex::escape(&release.program_name.to_string()),
+        regex::escape(&release.raw_version)
+    ))
+    .expect("Invalid regex");
+
+    let mut platforms: Vec<String> = checksums
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .filter_map(|name| re.captures(name))
+        .map(|caps| format!("{}/{}", &caps["os"], &caps["arch"]))
+        .collect();
+    platforms.sort();
+    platforms.dedup();
+    platforms
+}
+
+async fn resolve_constraint(client: &Client, args: &Args, constraint: &str) -> Result<()> {
+    let versions = args.get_version_list().get_versions(client, args).await?;
+    // Resolving is meant for scripts, so it never prompts even with --interactive-match
+    let resolve_args = Args {
+        list_all: args.list_all,
+        ..Default::default()
+    };
+    let pick =
+        |versions: &[ReleaseInfo]| get_version_from_constraint(&resolve_args, constraint, versions);
+    match pick_available(client, args, versions, pick).await? {
+        Some(release) => {
+            println!("{}", release.raw_version);
+            Ok(())
+        }
+        None => bail!(TfswitcherError::VersionNotFound(format!(
+            "no version matches constraint {constraint}"
+        ))),
+    }
+}
+
+async fn list_versions(client: &Client, args: &Args, target: &Target) -> Result<()> {
+    let mut versions = match args.cached_only {
+        true => {
+            let cache_dirs = [get_shared_cache_dir(), get_cache_dir(args)];
+            let cache_dirs: Vec<_> = cache_dirs.iter().flatten().map(PathBuf::as_path).collect();
+            get_cached_versions(&cache_dirs, args, target)?
+        }
+        false => {
+            let versions = args.get_version_list().get_versions(client, args).await?;
+            save_version_list(args, &versions);
+            versions
+        }
+    };
+    if let Some(since) = &args.since {
+        versions = filter_versions_since(versions, since)?;
+    }
+    if let Some(constraint) = &args.constraint {
+        versions = filter_versions_by_constraint(versions, constraint)?;
+    }
+    if let Some(count) = args.count {
+        versions.truncate(count);
+    }
+
+    if args.output == OutputFormat::Json {
+        println!("{}", format_versions_json(&versions)?);
+        return Ok(());
+    }
+
+    match args.format {
+        ListFormat::Plain => versions.get_versions().iter().for_each(|v| println!("{v}")),
+        ListFormat::Table => print!(
+            "{}",
+            format_versions_table(&versions, target, get_cache_dir(args).as_deref())
+        ),
+    }
+
+    Ok(())
+}
+
+async fn compare_versions(client: &Client, args: &Args) -> Result<()> {
+    let previous = load_version_list(args);
+    let versions = args.get_version_list().get_versions(client, args).await?;
+    save_version_list(args, &versions);
+
+    let Some(previous) = previous else {
+        status!(
+            "No previous listing to compare against, saved {} versions",
+            versions.len()
+        );
+        return Ok(());
+    };
+
+    let diff = diff_version_lists(&previous, &versions.get_versions());
+    match diff.is_empty() {
+        true => status!("No versions were added or removed since the last listing"),
+        false => diff.iter().for_each(|line| println!("{line}")),
+    }
+
+    Ok(())
+}
+
+fn get_version_list_name(args: &Args) -> String {
+    format!("versions_{}.txt", args.get_program_name())
+}
+
+fn load_version_list(args: &Args) -> Option<Vec<String>> {
+    let path = get_cache_dir(args)?.join(get_version_list_name(args));
+    let contents = fs::read_to_string(path).ok()?;
+    Some(contents.lines().map(str::to_owned).collect())
+}
+
+/// Records the listing for --compare, which is best effort like caching archives.
+fn save_version_list(args: &Args, versions: &[ReleaseInfo]) {
+    let Some(cache_dir) = get_cache_dir(args) else {
+        return;
+    };
+    let path = cache_dir.join(get_version_list_name(args));
+    let contents: String = versions
+        .iter()
+        .map(|r| format!("{}\n", r.raw_version))
+        .collect();
+    let result = fs::create_dir_all(&cache_dir).and_then(|_| write_atomically(&path, contents));
+    if let Err(e) = result {
+        status!("Unable to save version list to {path:?}: {e}");
+    }
+}
+
+/// Lists versions only in `current` as added and versions only in `previous` as removed.
+fn diff_version_lists(previous: &[String], current: &[String]) -> Vec<String> {
+    let added = current
+        .iter()
+        .filter(|v| !previous.contains(v))
+        .map(|v| format!("+ {v}"));
+    let removed = previous
+        .iter()
+        .filter(|v| !current.contains(v))
+        .map(|v| format!("- {v}"));
+    added.chain(removed).collect()
+}
+
+fn filter_versions_since(versions: Vec<ReleaseInfo>, since: &str) -> Result<Vec<ReleaseInfo>> {
+    let since = strip_version_prefix(since);
+    let since = Version::from_str(since).with_context(|| format!("invalid version {since}"))?;
+    Ok(versions.into_iter().filter(|r| r.version > since).collect())
+}
+
+/// Keeps versions released within `days` of `today`, both counted in days since the Unix epoch.
+fn filter_versions_newer_than(
+    versions: Vec<ReleaseInfo>,
+    days: u64,
+    today: i64,
+) -> Result<Vec<ReleaseInfo>> {
+    if !versions.is_empty() && versions.iter().all(|r| r.release_date.is_none()) {
+        bail!("release dates are not available, so versions cannot be filtered by age");
+    }
+
+    let cutoff = today.saturating_sub_unsigned(days);
+    Ok(versions
+        .into_iter()
+        .filter(|r| {
+            r.release_date
+                .as_deref()
+                .and_then(parse_days_since_epoch)
+                .is_some_and(|released| released >= cutoff)
+        })
+        .collect())
+}
+
+fn get_days_since_epoch() -> i64 {
+    let elapsed = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    (elapsed.as_secs() / 86400) as i64
+}
+
+/// Converts the date at the start of a timestamp like `2023-08-31T14:36:43Z` to days since the
+/// Unix epoch.
+fn parse_days_since_epoch(timestamp: &str) -> Option<i64> {
+    let date = timestamp.get(..10)?;
+    let mut parts = date.split('-').map(str::parse::<i64>);
+    let (year, month, day) = match (parts.next()?, parts.next()?, parts.next()?) {
+        (std::result::Result::Ok(y), std::result::Result::Ok(m), std::result::Result::Ok(d)) => {
+            (y, m, d)
+        }
+        _ => return None,
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Counts from March so that the leap day falls at the end of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+fn filter_versions_by_constraint(
+    versions: Vec<ReleaseInfo>,
+    constraint: &str,
+) -> Result<Vec<ReleaseInfo>> {
+    let req =
+        VersionReq::parse(constraint).map_err(|source| TfswitcherError::InvalidConstraint {
+            constraint: constraint.to_string(),
+            source,
+        })?;
+    Ok(versions
+        .into_iter()
+        .filter(|r| req.matches(&r.version))
+        .collect())
+}
+
+#[derive(Serialize)]
+struct VersionEntry<'a> {
+    version: &'a str,
+    prerelease: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    release_date: Option<&'a str>,
+}
+
+fn format_versions_json(versions: &[ReleaseInfo]) -> Result<String> {
+    let entries: Vec<_> = versions
+        .iter()
+        .map(|release| VersionEntry {
+            version: &release.raw_version,
+            prerelease: release.is_prerelease(),
+            release_date: release.release_date.as_deref(),
+        })
+        .collect();
+    serde_json::to_string(&entries).with_context(|| "failed to format versions as JSON")
+}
+
+fn format_versions_table(
+    versions: &[ReleaseInfo],
+    target: &Target,
+    cache_dir: Option<&Path>,
+) -> String {
+    const HEADERS: [&str; 3] = ["VERSION", "CHANNEL", "CACHED"];
+
+    let rows: Vec<[String; 3]> = versions
+        .iter()
+        .map(|release| {
+            let channel = match release.is_prerelease() {
+                true => "prerelease",
+                false => "stable",
+            };
+            let zip_name = release.get_zip_name(target);
+            let cached = match cache_dir.map(|dir| dir.join(&zip_name).exists()) {
+                Some(true) => "yes",
+                _ => "no",
+            };
+            [
+                release.raw_version.clone(),
+                channel.to_owned(),
+                cached.to_owned(),
+            ]
+        })
+        .collect();
+
+    let mut widths = HEADERS.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let mut table = String::new();
+    for row in std::iter::once(HEADERS.map(str::to_owned)).chain(rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+
+    table
+}
+
+async fn check_self_update(client: &Client, retry_on: &[u16]) -> Result<()> {
+    let release: GitHubRelease = get_http(client, SELF_LATEST_RELEASE_URL, retry_on)
+        .await?
+        .json()
+        .await
+        .with_context(|| "failed to get latest tfswitcher release")?;
+
+    if is_newer_version(clap::crate_version!(), &release.tag_name)? {
+        status!(
+            "tfswitcher {} is available, run `cargo install tfswitcher` to upgrade",
+            release.tag_name
+        );
+    }
+
+    Ok(())
+}
+
+fn is_newer_version(current: &str, tag_name: &str) -> Result<bool> {
+    let current =
+        Version::from_str(current).with_context(|| format!("invalid version {current}"))?;
+    let latest = tag_name.strip_prefix('v').unwrap_or(tag_name);
+    let latest = Version::from_str(latest).with_context(|| format!("invalid version {latest}"))?;
+
+    Ok(latest > current)
+}
+
+async fn audit_installed_version(
+    client: &Client,
+    args: &Args,
+    target: &Target,
+    program_path: &Path,
+) -> Result<()> {
+    let version = get_installed_version(program_path)?;
+    let release = ReleaseInfo::new(args.get_program_name(), version)?;
+    status!(
+        "Auditing {:?} {} at {program_path:?}",
+        release.program_name,
+        release.raw_version
+    );
+
+    let checksum = get_checksum(client, args, target, &release).await?;
+    let mut archive =
+        download_and_save_zip(client, args, target, &release, Some(&checksum)).await?;
+    let expected = read_archive_entry(&mut archive, &args.get_entry_name())?;
+    let actual = fs::read(program_path)
+        .with_context(|| format!("failed to read installed binary at {program_path:?}"))?;
+
+    if expected != actual {
+        bail!(
+            "installed binary at {program_path:?} does not match the official {:?} {} release",
+            release.program_name,
+            release.raw_version
+        );
+    }
+
+    status!("Installed binary matches the official release");
+    Ok(())
+}
+
+fn check_installed_version(installed: &ReleaseInfo, expected: &ReleaseInfo) -> Result<()> {
+    if installed.version != expected.version {
+        bail!(
+            "installed {:?} version is {}, expected {}",
+            expected.program_name,
+            installed.raw_version,
+            expected.raw_version
+        );
+    }
+
+    status!(
+        "Installed {:?} version {} matches",
+        expected.program_name,
+        expected.raw_version
+    );
+    Ok(())
+}
+
+fn get_installed_version(program_path: &Path) -> Result<String> {
+    let output = Command::new(program_path)
+        .arg("version")
+        .output()
+        .with_context(|| format!("failed to run {program_path:?}"))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    match parse_version_output(&stdout) {
+        Some(version) => Ok(version),
+        None => bail!("could not determine version of {program_path:?}"),
+    }
+}
+
+/// Parses the version from the first line of `terraform version` or `tofu version` output.
+fn parse_version_output(output: &str) -> Option<String> {
+    let re = Regex::new(r"^(?:Terraform|OpenTofu) v(?<version>\S+)").expect("Invalid regex");
+    let first_line = output.lines().next()?;
+
+    re.captures(first_line)
+        .and_then(|c| c.name("version"))
+        .map(|v| v.as_str().to_owned())
+}
+
+fn parse_config_arguments(cwd: PathBuf, args: &mut Args) -> Result<()> {
+    let mut config_version = None;
+    if let Some(config) = load_config_file(cwd, get_config_dir(), home::home_dir())? {
+        if args.binary_location.is_none() {
+            args.binary_location = config.binary_location
+        }
+        args.list_all |= config.list_all;
+        args.opentofu |= config.opentofu;
+        args.ipv4 |= config.ipv4;
+        args.ipv6 |= config.ipv6;
+        args.verify |= config.verify;
+        args.strict |= config.strict;
+        args.cosign |= config.cosign;
+        args.check_self_update |= config.check_self_update;
+        args.require_pin |= config.require_pin;
+        args.no_prompt |= config.no_prompt;
+        args.no_color |= config.no_color;
+        args.cache_binaries |= config.cache_binaries;
+        args.extract_all |= config.extract_all;
+        args.require_checksum |= config.require_checksum;
+        args.interactive_match |= config.interactive_match;
+        args.show_dates |= config.show_dates;
+        args.no_module_detect |= config.no_module_detect;
+        args.follow_symlink |= config.follow_symlink;
+        args.pick_fastest_mirror |= config.pick_fastest_mirror;
+        if args.mirror_url.is_empty() {
+            args.mirror_url = config.mirror_url
+        }
+        if args.ca_cert.is_empty() {
+            args.ca_cert = config.ca_cert
+        }
+        if args.retry_on.is_empty() {
+            for code in &config.retry_on {
+                if let Err(e) = check_retry_status(*code) {
+                    bail!("invalid retry_on in configuration file: {e}");
+                }
+            }
+            args.retry_on = config.retry_on
+        }
+        if args.pre_download.is_none() {
+            args.pre_download = config.pre_download
+        }
+        if args.program_names.is_empty() {
+            args.program_names = config.program_names
+        }
+        if args.install_mode.is_none() {
+            args.install_mode = config.install_mode
+        }
+        if args.min_tls.is_none() {
+            args.min_tls = config.min_tls
+        }
+        if args.jobs.is_none() {
+            if config.jobs == Some(0) {
+                bail!("invalid jobs in configuration file: expected at least 1");
+            }
+            args.jobs = config.jobs
+        }
+        if args.entry_name.is_none() {
+            args.entry_name = config.entry_name
+        }
+        if args.min_version.is_none() {
+            args.min_version = config.min_version
+        }
+        if args.policy_url.is_none() {
+            args.policy_url = config.policy_url
+        }
+        if args.count.is_none() {
+            args.count = config.count
+        }
+        if args.mode.is_none() {
+            args.mode = config.mode
+        }
+        if args.max_rate.is_none() {
+            args.max_rate = config.max_rate
+        }
+        if args.unix_proxy.is_none() {
+            args.unix_proxy = config.unix_proxy
+        }
+        if args.os.is_none() {
+            args.os = config.os
+        }
+        if args.arch.is_none() {
+            args.arch = config.arch
+        }
+        if args.user_agent.is_none() {
+            args.user_agent = config.user_agent
+        }
+        if args.cache_dir.is_none() {
+            args.cache_dir = config.cache_dir
+        }
+        if args.install_path_template.is_none() {
+            args.install_path_template = config.install_path_template
+        }
+        if args.libc.is_none() {
+            args.libc = config.libc
+        }
+        if args.default_version.is_none() {
+            args.default_version = config.default_version
+        }
+        if args.archive_name_template.is_none() {
+            args.archive_name_template = config.archive_name_template
+        }
+        if args.constraint.is_none() {
+            args.constraint = config.constraint
+        }
+        if args.constraint_file.is_none() {
+            args.constraint_file = config.constraint_file
+        }
+        if args.version_annotation.is_none() {
+            args.version_annotation = config.version_annotation
+        }
+        if args.env_file.is_none() {
+            args.env_file = config.env_file
+        }
+        if args.env_file_key.is_none() {
+            args.env_file_key = config.env_file_key
+        }
+        if args.version_env.is_none() {
+            args.version_env = config.version_env
+        }
+        config_version = config.install_version;
+    }
+
+    // A version from the environment takes precedence over the configuration file
+    if args.install_version.is_none() {
+        let value = args
+            .version_env
+            .as_deref()
+            .and_then(|name| env::var(name).ok());
+        args.install_version = get_version_from_env(value).or(config_version);
+    }
+
+    args.binary_location = args
+        .binary_location
+        .as_deref()
+        .map(expand_path)
+        .transpose()?;
+    args.cache_dir = args.cache_dir.as_deref().map(expand_path).transpose()?;
+
+    Ok(())
+}
+
+/// An empty variable is treated as unset, as CI systems often define inputs that weren't given.
+fn get_version_from_env(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.is_empty())
+}
+
+/// Formats the settings in the same format as the configuration file. One-shot flags are not part
+/// of the configuration and are left out.
+fn format_config(args: &Args) -> Result<String> {
+    toml::to_string(args).with_context(|| "failed to format configuration")
+}
+
+/// Expands a leading `~` and `$VAR` references in a path.
+fn expand_path(path: &Path) -> Result<PathBuf> {
+    expand_path_with(path, home::home_dir(), |name| env::var(name).ok())
+}
+
+/// Expands `~` to `home_dir` and `$VAR` to the value returned by `get_var`, which fails for unset
+/// variables like the shell's `set -u`.
+fn expand_path_with(
+    path: &Path,
+    home_dir: Option<PathBuf>,
+    get_var: impl Fn(&str) -> Option<String>,
+) -> Result<PathBuf> {
+    let Some(path_str) = path.to_str() else {
+        return Ok(path.to_path_buf());
+    };
+
+    let expanded = shellexpand::full_with_context(
+        path_str,
+        || home_dir.map(|dir| dir.to_string_lossy().into_owned()),
+        |name| get_var(name).map(Some).ok_or(env::VarError::NotPresent),
+    )
+    .with_context(|| format!("failed to expand path {path_str}"))?;
+    Ok(PathBuf::from(expanded.as_ref()))
+}
+
+fn load_config_file(
+    cwd: PathBuf,
+    config_dir: Option<PathBuf>,
+    home_dir: Option<PathBuf>,
+) -> Result<Option<Args>> {
+    let candidates = [
+        Some(cwd.join(CONFIG_FILE_NAME)),
+        config_dir.map(|dir| dir.join(XDG_CONFIG_FILE_NAME)),
+        home_dir.map(|home| home.join(CONFIG_FILE_NAME)),
+    ];
+
+    for path in candidates.into_iter().flatten() {
+        if !path.exists() {
+            continue;
+        }
+
+        let config = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read config file at {path:?}"))?;
+        let toml_file = toml::from_str(&config)
+            .with_context(|| format!("failed to parse config at {path:?}"))?;
+        return Ok(Some(toml_file));
+    }
+
+    Ok(None)
+}
+
+fn load_aliases(config_dir: &Path) -> Result<BTreeMap<String, String>> {
+    let path = config_dir.join(ALIASES_FILE_NAME);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read aliases file at {path:?}"))?;
+    toml::from_str(&contents).with_context(|| format!("failed to parse aliases at {path:?}"))
+}
+
+fn save_alias(config_dir: &Path, alias: &str, version: &str) -> Result<()> {
+    let alias = alias.strip_prefix('@').unwrap_or(alias);
+    if alias.is_empty() || version.starts_with('@') {
+        bail!("aliases must have a name and point to a version");
+    }
+
+    let mut aliases = load_aliases(config_dir)?;
+    aliases.insert(alias.to_string(), version.to_string());
+
+    let path = config_dir.join(ALIASES_FILE_NAME);
+    fs::create_dir_all(config_dir)
+        .with_context(|| format!("failed to create config directory {config_dir:?}"))?;
+    fs::write(&path, toml::to_string(&aliases)?)
+        .with_context(|| format!("failed to write aliases file at {path:?}"))?;
+
+    status!("Saved alias @{alias} for version {version}");
+    Ok(())
+}
+
+fn resolve_alias(config_dir: Option<&Path>, alias: &str) -> Result<String> {
+    let aliases = match config_dir {
+        Some(dir) => load_aliases(dir)?,
+        None => BTreeMap::new(),
+    };
+
+    match aliases.get(alias) {
+        Some(version) => {
+            status!("Alias @{alias} is version {version}");
+            Ok(version.clone())
+        }
+        None => bail!(TfswitcherError::VersionNotFound(format!(
+            "no version is saved for alias @{alias}"
+        ))),
+    }
+}
+
+/// Resolves an XDG base directory, falling back to `default_location` under the home directory.
+/// Relative paths in the environment variable are ignored, as required by the XDG spec.
+fn get_xdg_dir(
+    xdg_var: Option<OsString>,
+    home_dir: Option<PathBuf>,
+    default_location: &str,
+) -> Option<PathBuf> {
+    if let Some(dir) = xdg_var.map(PathBuf::from).filter(|dir| dir.is_absolute()) {
+        return Some(dir);
+    }
+
+    home_dir.map(|home| home.join(default_location))
+}
+
+fn get_bin_dir() -> Option<PathBuf> {
+    get_xdg_dir(
+        env::var_os("XDG_BIN_HOME"),
+        home::home_dir(),
+        &get_default_location(env::var("TFSWITCHER_DEFAULT_LOCATION").ok()),
+    )
+}
+
+/// Returns the home-relative bin directory, which can be overridden for users who keep binaries
+/// somewhere other than `.local/bin`.
+fn get_default_location(location: Option<String>) -> String {
+    location
+        .filter(|location| !location.is_empty() && Path::new(location).is_relative())
+        .unwrap_or_else(|| DEFAULT_LOCATION.to_owned())
+}
+
+fn get_cache_dir(args: &Args) -> Option<PathBuf> {
+    if args.cache_dir.is_some() {
+        return args.cache_dir.clone();
+    }
+
+    get_xdg_dir(
+        env::var_os("XDG_CACHE_HOME"),
+        home::home_dir(),
+        DEFAULT_CACHE_LOCATION,
+    )
+    .map(|dir| dir.join(APP_DIR_NAME))
+}
+
+/// Returns the shared archive cache, which is read before the per-user cache.
+fn get_shared_cache_dir() -> Option<PathBuf> {
+    env::var_os("TFSWITCHER_SHARED_CACHE")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .map(|dir| expand_path(&dir).unwrap_or(dir))
+}
+
+fn get_config_dir() -> Option<PathBuf> {
+    get_xdg_dir(
+        env::var_os("XDG_CONFIG_HOME"),
+        home::home_dir(),
+        DEFAULT_CONFIG_LOCATION,
+    )
+    .map(|dir| dir.join(APP_DIR_NAME))
+}
+
+fn get_program_name_candidates(args: &Args) -> Vec<String> {
+    match args.program_names.is_empty() {
+        true => vec![args.get_program_name().to_string()],
+        false => args.program_names.clone(),
+    }
+}
+
+fn find_terraform_program_path(args: &Args) -> Option<PathBuf> {
+    if args.binary_location.is_some() {
+        return args.binary_location.clone();
+    }
+
+    let program_name = args.get_program_name();
+    let candidates = get_program_name_candidates(args);
+
+    for candidate in &candidates {
+        if let Some(path) = pathsearch::find_executable_in_path(candidate) {
+            if candidates.len() > 1 {
+                status!("Found {candidate} at {path:?}");
+            }
+            return Some(path);
+        }
+    }
+
+    match get_bin_dir() {
+        Some(mut path) => {
+            path.push(&candidates[0]);
+            status!(
+                "Could not locate {program_name:?}, installing to {path:?}\nMake sure to include the directory in your $PATH environment variable"
+            );
+            Some(path)
+        }
+        None => None,
+    }
+}
+
+async fn get_version_to_install(client: &Client, args: &Args) -> Result<Option<ReleaseInfo>> {
+    if let Some(version) = &args.install_version {
+        if let Some(constraint) = &args.constraint {
+            status!("Warning: ignoring constraint {constraint} as version {version} was given");
+        }
+        let version = match version.strip_prefix('@') {
+            Some(alias) => resolve_alias(get_config_dir().as_deref(), alias)?,
+            None => version.clone(),
+        };
+        let release = ReleaseInfo::new(args.get_program_name(), version)?;
+        // A mirror may be all that is reachable, so the upstream API is left alone
+        if release.program_name == ProgramName::Terraform && args.mirror_url.is_empty() {
+            if let Err(e) = warn_if_withdrawn(client, &release, &args.retry_on).await {
+                status!("Unable to check release status: {e}");
+            }
+        }
+        return Ok(Some(release));
+    }
+
+    if let Some(channel) = args.channel {
+        // Pre-release channels need the full list regardless of --list-all
+        let list_args = Args {
+            list_all: true,
+            ..args.clone()
+        };
+        let versions = args
+            .get_version_list()
+            .get_versions(client, &list_args)
+            .await?;
+        let pick = |versions: &[ReleaseInfo]| Ok(get_newest_on_channel(versions, channel));
+        return match pick_available(client, args, versions, pick).await? {
+            Some(version) => Ok(Some(version)),
+            None => bail!(TfswitcherError::VersionNotFound(format!(
+                "no version found on the {channel:?} channel"
+            ))),
+        };
+    }
+
+    if let Some(index) = args.install_index {
+        let versions = args.get_version_list().get_versions(client, args).await?;
+        return get_version_by_index(&versions, index).map(Some);
+    }
+
+    if args.rollback {
+        let Some(program_path) = find_terraform_program_path(args) else {
+            bail!(
+                "could not find an installed {} to roll back",
+                args.get_program_name()
+            );
+        };
+        let installed = get_installed_version(&program_path)?;
+        let installed = ReleaseInfo::new(args.get_program_name(), installed)?;
+        let versions = args.get_version_list().get_versions(client, args).await?;
+        let pick = |versions: &[ReleaseInfo]| get_previous_version(versions, &installed).map(Some);
+        let Some(previous) = pick_available(client, args, versions, pick).await? else {
+            unreachable!("get_previous_version fails instead of returning nothing");
+        };
+        status!(
+            "Rolling back from {} to {}",
+            installed.raw_version,
+            previous.raw_version
+        );
+        return Ok(Some(previous));
+    }
+
+    if let Some(constraint) = &args.constraint {
+        let versions = args.get_version_list().get_versions(client, args).await?;
+        let pick =
+            |versions: &[ReleaseInfo]| get_version_from_constraint(args, constraint, versions);
+        return match pick_available(client, args, versions, pick).await? {
+            Some(version) => Ok(Some(version)),
+            None => bail!(TfswitcherError::VersionNotFound(format!(
+                "no version matches constraint {constraint}"
+            ))),
+        };
+    }
+
+    if let Some(path) = &args.constraint_file {
+        let versions = args.get_version_list().get_versions(client, args).await?;
+        let constraint = get_constraint_from_file(path)?;
+        status!("Constraint from {path:?} is {constraint}");
+        let pick =
+            |versions: &[ReleaseInfo]| get_version_from_constraint(args, &constraint, versions);
+        return match pick_available(client, args, versions, pick).await? {
+            Some(version) => Ok(Some(version)),
+            None => bail!(TfswitcherError::VersionNotFound(format!(
+                "no version matches constraint {constraint} from {path:?}"
+            ))),
+        };
+    }
+
+    if let Some(path) = &args.version_annotation {
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("failed to read {path:?}"))?;
+        let Some(version) = parse_version_annotation(&contents, &args.get_program_name()) else {
+            bail!(TfswitcherError::VersionNotFound(format!(
+                "no {}-version annotation found in {path:?}",
+                args.get_program_name()
+            )));
+        };
+        status!("Found version {version} annotated in {path:?}");
+        return Ok(Some(ReleaseInfo::new(args.get_program_name(), version)?));
+    }
+
+    if let Some(path) = &args.env_file {
+        if let Some(release) = get_version_from_env_file(args, path)? {
+            return Ok(Some(release));
+        }
+    }
+
+    // An ambient tfenv variable is weaker than the flags passed on the command line
+    let tfenv_variable = args.get_program_name().get_tfenv_variable_name();
+    if let Some(spec) = env::var(tfenv_variable).ok().filter(|v| !v.is_empty()) {
+        if !is_tfenv_keyword(&spec) {
+            return Ok(Some(ReleaseInfo::new(args.get_program_name(), spec)?));
+        }
+
+        let versions = args.get_version_list().get_versions(client, args).await?;
+        let module_constraint = match args.no_module_detect {
+            true => None,
+            false => get_module_constraint(Path::new("."))?,
+        };
+        let pick = |versions: &[ReleaseInfo]| {
+            get_version_from_tfenv_spec(&spec, versions, module_constraint.as_deref())
+        };
+        return match pick_available(client, args, versions, pick).await? {
+            Some(version) => Ok(Some(version)),
+            None => bail!(TfswitcherError::VersionNotFound(format!(
+                "no version matches {spec} from {tfenv_variable}"
+            ))),
+        };
+    }
+
+    let cwd = env::current_dir().with_context(|| "failed to get current directory")?;
+    let tool_version = match args.no_module_detect {
+        true => None,
+        false => get_version_from_tool_versions(&cwd, &args.get_program_name())?,
+    };
+    if let Some(version) = tool_version.as_ref().filter(|v| !v.starts_with("latest")) {
+        return Ok(Some(ReleaseInfo::new(
+            args.get_program_name(),
+            version.into(),
+        )?));
+    }
+
+    let versions = args.get_version_list().get_versions(client, args).await?;
+
+    if let Some(spec) = tool_version {
+        let pick = |versions: &[ReleaseInfo]| Ok(get_latest_version(&spec, versions));
+        match pick_available(client, args, versions.clone(), pick).await? {
+            Some(version) => return Ok(Some(version)),
+            None => {
+                check_constraint_within_policy(args, &spec)?;
+                status!("No version matches {spec} from {TOOL_VERSIONS_FILE_NAME}");
+            }
+        }
+    }
+
+    if !args.no_module_detect {
+        if let Some(constraint) = get_constraint_from_terragrunt(Path::new("."))? {
+            status!("Terragrunt constraint is {constraint}");
+            let pick =
+                |versions: &[ReleaseInfo]| get_version_from_constraint(args, &constraint, versions);
+            if let Some(version) = pick_available(client, args, versions.clone(), pick).await? {
+                return Ok(Some(version));
+            }
+        }
+
+        let pick =
+            |versions: &[ReleaseInfo]| get_version_from_module(args, Path::new("."), versions);
+        if let Some(version) = pick_available(client, args, versions.clone(), pick).await? {
+            return Ok(Some(version));
+        }
+
+        // An implicit .env file comes last, so the dedicated version files win over it
+        let env_file = Path::new(ENV_FILE_NAME);
+        if env_file.is_file() {
+            if let Some(version) = get_version_from_env_file(args, env_file)? {
+                return Ok(Some(version));
+            }
+        }
+    }
+
+    if args.require_pin {
+        bail!("no version is pinned by an argument, constraint, version file or module");
+    }
+
+    let count = args.count.unwrap_or(versions.len()).min(versions.len());
+    let release = get_version_from_user_prompt(args, &versions[..count])?;
+    if let Some(release) = release
+        .as_ref()
+        .filter(|r| r.program_name == ProgramName::Terraform)
+    {
+        if args.mirror_url.is_empty() {
+            if let Err(e) = warn_if_withdrawn(client, release, &args.retry_on).await {
+                status!("Unable to check release status: {e}");
+            }
+        }
+    }
+    Ok(release)
+}
+
+async fn resolve_version_or_bail(client: &Client, args: &Args) -> Result<ReleaseInfo> {
+    match get_version_to_install(client, args).await? {
+        Some(release) => Ok(release),
+        None => bail!(TfswitcherError::VersionNotFound(
+            "no version to install".into()
+        )),
+    }
+}
+
+/// Picks a version from the list with `pick`, falling back to the next pick when HashiCorp has
+/// withdrawn the chosen Terraform release. Only the chosen release is looked up, so this costs a
+/// single request unless releases are skipped.
+async fn pick_available(
+    client: &Client,
+    args: &Args,
+    mut versions: Vec<ReleaseInfo>,
+    pick: impl Fn(&[ReleaseInfo]) -> Result<Option<ReleaseInfo>>,
+) -> Result<Option<ReleaseInfo>> {
+    loop {
+        let Some(release) = pick(&versions)? else {
+            return Ok(None);
+        };
+        // Release dates come from the releases API, which has already dropped withdrawn releases
+        let is_checked = release.release_date.is_some();
+        // A mirror may be all that is reachable, so the upstream API is left alone
+        if release.program_name != ProgramName::Terraform
+            || !args.mirror_url.is_empty()
+            || is_checked
+        {
+            return Ok(Some(release));
+        }
+
+        match is_release_withdrawn(client, &release, &args.retry_on).await {
+            std::result::Result::Ok(true) => {
+                status!(
+                    "Skipping Terraform {} as it has been withdrawn by HashiCorp",
+                    release.raw_version
+                );
+                versions.retain(|r| r.raw_version != release.raw_version);
+            }
+            std::result::Result::Ok(false) => return Ok(Some(release)),
+            Err(e) => {
+                status!("Unable to check release status: {e}");
+                return Ok(Some(release));
+            }
+        }
+    }
+}
+
+fn get_newest_on_channel(versions: &[ReleaseInfo], channel: Channel) -> Option<ReleaseInfo> {
+    versions
+        .iter()
+        .find(|r| Channel::of(r) == Some(channel))
+        .cloned()
+}
+
+/// Finds the newest stable release older than `installed` in a list sorted newest first.
+fn get_previous_version(versions: &[ReleaseInfo], installed: &ReleaseInfo) -> Result<ReleaseInfo> {
+    match versions
+        .iter()
+        .find(|r| !r.is_prerelease() && r.version < installed.version)
+    {
+        Some(version) => Ok(version.clone()),
+        None => bail!(TfswitcherError::VersionNotFound(format!(
+            "no stable release is older than the installed version {}",
+            installed.raw_version
+        ))),
+    }
+}
+
+/// Picks a version from a list sorted newest first, so that 0 is the latest.
+fn get_version_by_index(versions: &[ReleaseInfo], index: i64) -> Result<ReleaseInfo> {
+    let found = usize::try_from(index)
+        .ok()
+        .and_then(|i| versions.get(i).cloned());
+    match found {
+        Some(version) => Ok(version),
+        None => bail!(TfswitcherError::VersionNotFound(format!(
+            "index {index} is out of range, expected 0 to {}",
+            versions.len().saturating_sub(1)
+        ))),
+    }
+}
+
+/// Searches `cwd` and its parents for an asdf `.tool-versions` file with an entry for the program.
+fn get_version_from_tool_versions(
+    cwd: &Path,
+    program_name: &ProgramName,
+) -> Result<Option<String>> {
+    for dir in cwd.ancestors() {
+        let path = dir.join(TOOL_VERSIONS_FILE_NAME);
+        if !path.exists() {
+            continue;
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {TOOL_VERSIONS_FILE_NAME} at {path:?}"))?;
+        if let Some(version) = parse_tool_versions(&contents, program_name.get_asdf_plugin_name()) {
+            status!("Found {program_name:?} {version} in {path:?}");
+            return Ok(Some(version));
+        }
+    }
+
+    Ok(None)
+}
+
+fn parse_tool_versions(contents: &str, plugin_name: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            // asdf allows fallback versions after the first, only the preferred one is used
+            Some(name) if name == plugin_name => fields.next().map(str::to_owned),
+            _ => None,
+        }
+    })
+}
+
+/// Resolves asdf's `latest` and `latest:<prefix>` keywords to the newest matching stable version.
+fn get_latest_version(spec: &str, versions: &[ReleaseInfo]) -> Option<ReleaseInfo> {
+    let prefix = match spec.split_once(':') {
+        Some(("latest", prefix)) => prefix,
+        _ if spec == "latest" => "",
+        _ => return None,
+    };
+
+    versions
+        .iter()
+        .filter(|r| !r.is_prerelease())
+        .find(|r| r.raw_version.starts_with(prefix))
+        .cloned()
+}
+
+fn get_constraint_from_terragrunt(cwd: &Path) -> Result<Option<String>> {
+    let path = cwd.join(TERRAGRUNT_FILE_NAME);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {TERRAGRUNT_FILE_NAME} at {path:?}"))?;
+    Ok(parse_terragrunt_constraint(&contents))
+}
+
+fn parse_terragrunt_constraint(contents: &str) -> Option<String> {
+    let re = Regex::new(r#"(?m)^\s*terraform_version_constraint\s*=\s*"(?<constraint>[^"]*)""#)
+        .expect("Invalid regex");
+
+    re.captures(contents)
+        .and_then(|c| c.name("constraint"))
+        .map(|c| c.as_str().to_owned())
+}
+
+/// Reads the `required_version` attribute of a `terraform` or `packer` block from an HCL file, or
+/// from a JSON file if the extension is `.json`.
+fn get_constraint_from_file(path: &Path) -> Result<String> {
+    let contents = fs::read_to_string(path).with_context(|| format!("failed to read {path:?}"))?;
+    let constraint = match path.extension().is_some_and(|ext| ext == "json") {
+        true => parse_json_constraint(&contents)
+            .with_context(|| format!("failed to parse {path:?} as JSON"))?,
+        false => parse_hcl_constraint(&contents)
+            .with_context(|| format!("failed to parse {path:?} as HCL"))?,
+    };
+
+    match constraint {
+        Some(constraint) => Ok(constraint),
+        None => bail!("no required_version constraint found in {path:?}"),
+    }
+}
+
+fn parse_hcl_constraint(contents: &str) -> Result<Option<String>> {
+    let body = hcl::parse(contents)?;
+    Ok(body
+        .blocks()
+        .filter(|block| matches!(block.identifier(), "terraform" | "packer"))
+        .flat_map(|block| block.body().attributes())
+        .find(|attr| attr.key() == "required_version")
+        .map(|attr| attr.expr().to_string().replace('"', "")))
+}
+
+fn parse_json_constraint(contents: &str) -> Result<Option<String>> {
+    let value: serde_json::Value = serde_json::from_str(contents)?;
+    let blocks = ["terraform", "packer"]
+        .iter()
+        .filter_map(|name| value.get(name))
+        .flat_map(|block| match block {
+            serde_json::Value::Array(blocks) => blocks.iter().collect(),
+            block => vec![block],
+        });
+
+    for block in blocks {
+        if let Some(constraint) = block.get("required_version").and_then(|v| v.as_str()) {
+            return Ok(Some(constraint.to_owned()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Finds a comment like `# terraform-version: 1.5.7`, or `# tofu-version: 1.6.0` for OpenTofu,
+/// in any of the usual comment styles.
+fn parse_version_annotation(contents: &str, program_name: &ProgramName) -> Option<String> {
+    let re = Regex::new(&format!(
+        r"(?m)^\s*(?:#|//|--|;)\s*{program_name}-version:\s*(?<version>\S+)"
+    ))
+    .expect("Invalid regex");
+
+    re.captures(contents)
+        .and_then(|c| c.name("version"))
+        .map(|v| v.as_str().to_owned())
+}
+
+/// Reads the version from the env file given with --env-file, or from `.env` in the current
+/// directory. A missing entry is only an error for the file given with --env-file, as the
+/// implicit `.env` file may be there for other tools.
+fn get_version_from_env_file(args: &Args, path: &Path) -> Result<Option<ReleaseInfo>> {
+    let program_name = args.get_program_name();
+    let key = args
+        .env_file_key
+        .as_deref()
+        .unwrap_or(program_name.get_env_file_key());
+
+    let contents = fs::read_to_string(path).with_context(|| format!("failed to read {path:?}"))?;
+    let Some(version) = parse_env_file_version(&contents, key) else {
+        if args.env_file.is_some() {
+            bail!(TfswitcherError::VersionNotFound(format!(
+                "no {key} entry found in {path:?}"
+            )));
+        }
+        return Ok(None);
+    };
+
+    status!("Found version {version} for {key} in {path:?}");
+    ReleaseInfo::new(program_name, version.clone())
+        .with_context(|| format!("invalid version {version} for {key} in {path:?}"))
+        .map(Some)
+}
+
+/// Finds an assignment like `TERRAFORM_VERSION=1.5.7` in a .env file, or `TERRAFORM_VERSION ?=
+/// 1.5.7` in a Makefile.
+fn parse_env_file_version(contents: &str, key: &str) -> Option<String> {
+    let re = Regex::new(&format!(
+        r#"(?m)^\s*(?:export\s+)?{}\s*(?:\?=|:=|=)\s*["']?(?<version>[^"'\s#]+)"#,
+        regex::escape(key)
+    ))
+    .expect("Invalid regex");
+
+    re.captures(contents)
+        .and_then(|c| c.name("version"))
+        .map(|v| v.as_str().to_owned())
+}
+
+fn is_tfenv_keyword(spec: &str) -> bool {
+    spec.starts_with("latest") || spec == "min-required"
+}
+
+/// Resolves the `latest`, `latest:<regex>`, `latest-allowed` and `min-required` values supported
+/// by tfenv, the last two against the module constraint.
+fn get_version_from_tfenv_spec(
+    spec: &str,
+    versions: &[ReleaseInfo],
+    module_constraint: Option<&str>,
+) -> Result<Option<ReleaseInfo>> {
+    if let Some(pattern) = spec.strip_prefix("latest:") {
+        let re = Regex::new(pattern).with_context(|| format!("invalid version regex {pattern}"))?;
+        return Ok(versions
+            .iter()
+            .find(|r| re.is_match(&r.raw_version))
+            .cloned());
+    }
+
+    if spec == "latest" {
+        return Ok(get_latest_version(spec, versions));
+    }
+
+    let Some(constraint) = module_constraint else {
+        bail!("{spec} requires a module with a required_version constraint");
+    };
+    let req =
+        VersionReq::parse(constraint).map_err(|source| TfswitcherError::InvalidConstraint {
+            constraint: constraint.to_string(),
+            source,
+        })?;
+    let mut matches = versions.iter().filter(|r| req.matches(&r.version));
+
+    match spec {
+        "latest-allowed" => Ok(matches.next().cloned()),
+        "min-required" => Ok(matches.next_back().cloned()),
+        _ => bail!("unsupported tfenv version {spec}"),
+    }
+}
+
+fn get_module_constraint(cwd: &Path) -> Result<Option<String>> {
+    let module =
+        tfconfig::load_module(cwd, false).with_context(|| "failed to load terraform modules")?;
+    Ok(module.required_core.first().cloned())
+}
+
+fn get_version_from_module(
+    args: &Args,
+    cwd: &Path,
+    versions: &[ReleaseInfo],
+) -> Result<Option<ReleaseInfo>> {
+    let Some(version_constraint) = get_module_constraint(cwd)? else {
+        return Ok(None);
+    };
+
+    status!("Module constraint is {version_constraint}");
+
+    get_version_from_constraint(args, &version_constraint, versions)
+}
+
+fn get_version_from_constraint(
+    args: &Args,
+    constraint: &str,
+    versions: &[ReleaseInfo],
+) -> Result<Option<ReleaseInfo>> {
+    let req =
+        VersionReq::parse(constraint).map_err(|source| TfswitcherError::InvalidConstraint {
+            constraint: constraint.to_string(),
+            source,
+        })?;
+    let matches: Vec<_> = versions
+        .iter()
+        .filter(|r| req.matches(&r.version))
+        .cloned()
+        .collect();
+
+    if matches.is_empty() {
+        check_constraint_within_policy(args, constraint)?;
+        status!("{}", describe_unmatched_constraint(constraint, versions));
+        // Pre-releases are only listed with --list-all, and semver only lets a constraint match
+        // them if it names a pre-release itself
+        if !args.list_all && has_prerelease_comparator(&req) {
+            status!(
+                "Warning: constraint {constraint} only matches pre-release versions, use --list-all to include them"
+            );
+        }
+        return Ok(None);
+    }
+
+    let version = match matches.len() > 1 && args.interactive_match && can_prompt(args) {
+        true => get_version_from_user_prompt(args, &matches)?,
+        false => matches.into_iter().next(),
+    };
+
+    if let Some(release) = version.as_ref().filter(|r| r.is_prerelease()) {
+        status!(
+            "Warning: constraint {constraint} resolved to pre-release version {}",
+            release.raw_version
+        );
+    }
+
+    Ok(version)
+}
+
+fn describe_unmatched_constraint(constraint: &str, versions: &[ReleaseInfo]) -> String {
+    let description = format!(
+        "No version matches constraint {constraint} out of {} versions considered",
+        versions.len()
+    );
+    match versions.first() {
+        Some(newest) => format!("{description}, the newest is {}", newest.raw_version),
+        None => description,
+    }
+}
+
+fn has_prerelease_comparator(req: &VersionReq) -> bool {
+    req.comparators.iter().any(|c| !c.pre.is_empty())
+}
+
+fn format_version_with_date(release: &ReleaseInfo) -> String {
+    match release
+        .release_date
+        .as_deref()
+        .and_then(|date| date.get(..10))
+    {
+        Some(date) => format!("{} ({date})", release.raw_version),
+        None => release.raw_version.clone(),
+    }
+}
+
+/// Marks pre-releases in yellow, or with a text marker when color is disabled.
+fn format_prompt_item(release: &ReleaseInfo, show_dates: bool, color: bool) -> String {
+    let item = match show_dates {
+        true => format_version_with_date(release),
+        false => release.raw_version.clone(),
+    };
+
+    match (release.is_prerelease(), color) {
+        (true, true) => console::style(item).yellow().to_string(),
+        (true, false) => format!("{item} (pre)"),
+        (false, _) => item,
+    }
+}
+
+fn use_color(args: &Args) -> bool {
+    !args.no_color && env::var_os("NO_COLOR").map_or(true, |v| v.is_empty())
+}
+
+fn can_prompt(args: &Args) -> bool {
+    !args.no_prompt && io::stdin().is_terminal()
+}
+
+fn get_prompt_theme(color: bool) -> Box<dyn Theme> {
+    match color {
+        true => Box::<ColorfulTheme>::default(),
+        false => Box::new(SimpleTheme),
+    }
+}
+
+/// Asks before an action that removes or overwrites files, unless --yes is given. Without a
+/// terminal to ask on, the action is refused instead.
+fn confirm(args: &Args, prompt: &str) -> Result<()> {
+    if args.yes {
+        return Ok(());
+    }
+    if !can_prompt(args) {
+        bail!("{prompt} Refusing to continue without confirmation, pass --yes to confirm");
+    }
+
+    let confirmed = Confirm::with_theme(get_prompt_theme(use_color(args)).as_ref())
+        .with_prompt(prompt)
+        .default(false)
+        .interact_opt()
+        .with_context(|| "failed to get confirmation from user prompt")?;
+    match confirmed {
+        Some(true) => Ok(()),
+        _ => bail!("aborted"),
+    }
+}
+
+fn confirm_removal(args: &Args, paths: &[PathBuf]) -> Result<()> {
+    if args.dry_run || paths.is_empty() {
+        return Ok(());
+    }
+    if !args.yes {
+        paths.iter().for_each(|path| status!("{path:?}"));
+    }
+
+    confirm(args, &format!("Remove these {} files?", paths.len()))
+}
+
+/// `TFSWITCHER_ASSUME_DEFAULT` picks the prompt's default instead of asking, e.g. in CI.
+fn is_assume_default(value: Option<&str>) -> bool {
+    value.is_some_and(|v| !v.is_empty() && v != "0" && !v.eq_ignore_ascii_case("false"))
+}
+
+fn get_version_from_user_prompt(
+    args: &Args,
+    versions: &[ReleaseInfo],
+) -> Result<Option<ReleaseInfo>> {
+    let assume_default = env::var("TFSWITCHER_ASSUME_DEFAULT").ok();
+    if !args.no_prompt && is_assume_default(assume_default.as_deref()) {
+        let selection = get_default_selection(versions, args.default_version.as_deref());
+        let release = versions.get(selection).cloned();
+        if let Some(release) = &release {
+            status!(
+                "Selected {} as TFSWITCHER_ASSUME_DEFAULT is set",
+                release.raw_version
+            );
+        }
+        return Ok(release);
+    }
+
+    if !can_prompt(args) {
+        bail!(TfswitcherError::VersionNotFound(
+            "no version specified and cannot prompt in non-interactive mode, pass a version or --constraint".into()
+        ));
+    }
+
+    let color = use_color(args);
+    let items: Vec<String> = versions
+        .iter()
+        .map(|release| format_prompt_item(release, args.show_dates, color))
+        .collect();
+    match Select::with_theme(get_prompt_theme(color).as_ref())
+        .with_prompt(format!(
+            "Select a {:?} version to install",
+            args.get_program_name()
+        ))
+        .items(&items)
+        .default(get_default_selection(
+            versions,
+            args.default_version.as_deref(),
+        ))
+        .interact_opt()
+        .with_context(|| "failed to get version from user prompt")?
+    {
+        Some(selection) => Ok(versions.get(selection).cloned()),
+        None => Ok(None),
+    }
+}
+
+/// Finds the prompt item to pre-select, falling back to the newest version.
+fn get_default_selection(versions: &[ReleaseInfo], default_version: Option<&str>) -> usize {
+    let Some(default_version) = default_version else {
+        return 0;
+    };
+
+    let wanted = Version::from_str(strip_version_prefix(default_version)).ok();
+    match versions
+        .iter()
+        .position(|r| Some(&r.version) == wanted.as_ref())
+    {
+        Some(index) => index,
+        None => {
+            status!("Warning: default version {default_version} is not in the list, selecting the newest instead");
+            0
+        }
+    }
+}
+
+async fn install_version(
+    client: &Client,
+    args: &Args,
+    target: &Target,
+    program_path: &Path,
+    release: ReleaseInfo,
+) -> Result<PathBuf> {
+    check_min_version(args.min_version.as_deref(), &release)?;
+    check_policy(args.policy_constraint.as_deref(), &release)?;
+    if args.arch.is_none() && target.os == "darwin" && target.arch == "amd64" && is_rosetta() {
+        status!("Warning: running under Rosetta, so the amd64 build will be emulated, pass --arch arm64 for a native binary");
+    }
+    if let Some(command) = &args.pre_download {
+        run_pre_download_hook(command, target, &release)?;
+    }
+    if let Some(cached_binary) = get_cached_binary(args, target, &release) {
+        return install_cached_binary(args, program_path, &release.raw_version, &cached_binary);
+    }
+
+    let archive = get_zip(client, args, target, &release).await?;
+    install_archive(args, program_path, &release.raw_version, archive)
+}
+
+/// Runs the hook through the shell with the release about to be installed in its environment.
+fn run_pre_download_hook(command: &str, target: &Target, release: &ReleaseInfo) -> Result<()> {
+    let (shell, flag) = match cfg!(windows) {
+        true => ("cmd", "/C"),
+        false => ("sh", "-c"),
+    };
+    let status = Command::new(shell)
+        .args([flag, command])
+        .env("TFSWITCHER_PRODUCT", release.program_name.to_string())
+        .env("TFSWITCHER_VERSION", &release.raw_version)
+        .env("TFSWITCHER_OS", &target.os)
+        .env("TFSWITCHER_ARCH", &target.arch)
+        .status()
+        .with_context(|| format!("failed to run pre-download hook {command:?}"))?;
+    if !status.success() {
+        bail!("pre-download hook {command:?} failed with {status}, aborting install");
+    }
+
+    Ok(())
+}
+
+/// Detects an x86_64 process translated by Rosetta on Apple Silicon.
+#[cfg(target_os = "macos")]
+fn is_rosetta() -> bool {
+    Command::new("sysctl")
+        .args(["-in", "sysctl.proc_translated"])
+        .output()
+        .is_ok_and(|output| is_proc_translated(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn is_rosetta() -> bool {
+    false
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn is_proc_translated(sysctl_output: &str) -> bool {
+    sysctl_output.trim() == "1"
+}
+
+/// Returns the contents of the cached binary for a release when --cache-binaries is set and it
+/// matches the checksum recorded in the cache manifest. The binary cache is bypassed when the
+/// archive is to be verified, as the cached binary can't be checked against a published checksum.
+fn get_cached_binary(args: &Args, target: &Target, release: &ReleaseInfo) -> Option<Vec<u8>> {
+    let is_verification_requested =
+        args.verify || args.cosign || args.require_checksum || args.checksum.is_some();
+    // Only the main binary is cached, so companion executables need the archive
+    if !args.cache_binaries || is_verification_requested || args.extract_all {
+        return None;
+    }
+
+    let cache_dir = get_cache_dir(args)?;
+    let path = get_binary_cache_path(args, target, &release.raw_version)?;
+    let contents = fs::read(&path).ok()?;
+    let manifest_name = get_binary_manifest_name(args, target, &release.raw_version);
+    match get_manifest_checksum(&cache_dir, &manifest_name) {
+        Some(checksum) if checksum.verify(&contents) => Some(contents),
+        _ => {
+            status!("Cached binary {path:?} does not match the cache manifest, removing it and extracting the archive");
+            let _ = fs::remove_file(&path);
+            None
+        }
+    }
+}
+
+/// Binaries extracted from another entry with --entry-name are cached under a name of their own.
+fn get_binary_cache_name(args: &Args, target: &Target, version: &str) -> String {
+    let program_name = args.get_program_name().to_string();
+    let name = format!("{program_name}_{version}_{}_{}", target.os, target.arch);
+    match args.get_entry_name() {
+        entry_name if entry_name == program_name => name,
+        entry_name => format!("{name}_{}", entry_name.replace(['/', '\\'], "-")),
+    }
+}
+
+/// Cached binaries are recorded in the cache manifest under their path in the cache directory.
+fn get_binary_manifest_name(args: &Args, target: &Target, version: &str) -> String {
+    format!(
+        "{BINARY_CACHE_DIR_NAME}/{}",
+        get_binary_cache_name(args, target, version)
+    )
+}
+
+fn get_binary_cache_path(args: &Args, target: &Target, version: &str) -> Option<PathBuf> {
+    let name = get_binary_cache_name(args, target, version);
+    get_cache_dir(args).map(|dir| dir.join(BINARY_CACHE_DIR_NAME).join(name))
+}
+
+/// Keeps a copy of an extracted binary, which is best effort like caching archives.
+fn cache_extracted_binary(args: &Args, version: &str, install_path: &Path) {
+    let target = Target::from_args(args);
+    let (Some(cache_dir), Some(path)) = (
+        get_cache_dir(args),
+        get_binary_cache_path(args, &target, version),
+    ) else {
+        return;
+    };
+
+    let manifest_name = get_binary_manifest_name(args, &target, version);
+    let result = path
+        .parent()
+        .into_iter()
+        .try_for_each(fs::create_dir_all)
+        .and_then(|_| fs::read(install_path))
+        .map_err(anyhow::Error::from)
+        .and_then(|contents| {
+            write_atomically(&path, &contents)?;
+            update_cache_manifest(&cache_dir, &manifest_name, &contents)
+        });
+    match result {
+        Err(e) => status!("Unable to cache extracted binary: {e}"),
+        _ => status!("Cached extracted binary to {path:?}"),
+    }
+}
+
+/// Enforces the version floor however the version was resolved.
+fn check_min_version(min_version: Option<&str>, release: &ReleaseInfo) -> Result<()> {
+    let Some(min_version) = min_version else {
+        return Ok(());
+    };
+    let floor = Version::from_str(strip_version_prefix(min_version))
+        .with_context(|| format!("invalid minimum version {min_version}"))?;
+    if release.version < floor {
+        bail!(
+            "{:?} {} is older than the minimum version {min_version}",
+            release.program_name,
+            release.raw_version
+        );
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+struct Policy {
+    terraform: Option<PolicyEntry>,
+    opentofu: Option<PolicyEntry>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct PolicyEntry {
+    constraint: String,
+}
+
+async fn get_policy_constraint(
+    client: &Client,
+    url: &str,
+    program_name: &ProgramName,
+    retry_on: &[u16],
+) -> Result<Option<String>> {
+    let contents = get_http(client, url, retry_on)
+        .await?
+        .text()
+        .await
+        .with_context(|| format!("failed to read policy from {url}"))?;
+    let policy = parse_policy(&contents).with_context(|| format!("invalid policy at {url}"))?;
+    let entry = match program_name {
+        ProgramName::Terraform => policy.terraform,
+        ProgramName::OpenTofu => policy.opentofu,
+    };
+    let Some(PolicyEntry { constraint }) = entry else {
+        return Ok(None);
+    };
+
+    // Parse the constraint now so that a broken policy fails before anything is resolved
+    VersionReq::parse(&constraint).map_err(|source| TfswitcherError::InvalidConstraint {
+        constraint: constraint.clone(),
+        source,
+    })?;
+    status!("Policy constraint is {constraint}");
+    Ok(Some(constraint))
+}
+
+/// Policies are JSON or TOML, told apart by JSON's leading brace.
+fn parse_policy(contents: &str) -> Result<Policy> {
+    match contents.trim_start().starts_with('{') {
+        true => Ok(serde_json::from_str(contents)?),
+        false => Ok(toml::from_str(contents)?),
+    }
+}
+
+fn check_policy(constraint: Option<&str>, release: &ReleaseInfo) -> Result<()> {
+    let Some(constraint) = constraint else {
+        return Ok(());
+    };
+    let req =
+        VersionReq::parse(constraint).map_err(|source| TfswitcherError::InvalidConstraint {
+            constraint: constraint.to_string(),
+            source,
+        })?;
+    if !req.matches(&release.version) {
+        bail!(
+            "{:?} {} is outside the policy constraint {constraint}",
+            release.program_name,
+            release.raw_version
+        );
+    }
+
+    Ok(())
+}
+
+/// The version list is already narrowed to the policy, so a constraint that matches none of it
+/// falls entirely outside the policy.
+fn check_constraint_within_policy(args: &Args, constraint: &str) -> Result<()> {
+    if let Some(policy) = &args.policy_constraint {
+        bail!("no version matches both {constraint} and the policy constraint {policy}");
+    }
+
+    Ok(())
+}
+
+/// Formats a line of a SHA256SUMS file.
+fn format_checksum_line(buffer: &[u8], file_name: &str) -> String {
+    let checksum = Checksum::Sha256(Sha256::digest(buffer).into());
+    format!("{checksum}  {file_name}")
+}
+
+async fn download_only(
+    client: &Client,
+    args: &Args,
+    target: &Target,
+    release: &ReleaseInfo,
+) -> Result<()> {
+    get_zip(client, args, target, release).await?;
+
+    let zip_name = release.get_zip_name(target);
+    let cached_path = [get_shared_cache_dir(), get_cache_dir(args)]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join(&zip_name))
+        .find(|path| path.exists());
+    match cached_path {
+        Some(path) => {
+            println!("{}", path.display());
+            Ok(())
+        }
+        None => bail!("{zip_name} was downloaded but could not be cached"),
+    }
+}
+
+async fn download_targets(
+    client: &Client,
+    args: &Args,
+    target: &Target,
+    release: &ReleaseInfo,
+) -> Result<()> {
+    let results: Vec<_> = stream::iter(&args.targets)
+        .map(|spec| async move {
+            let (os, arch) = parse_target(spec)?;
+            // The host's C library only applies to targets for the host's OS
+            let libc = args.libc.or(target.libc.map(|_| Libc::detect(&os)));
+            let target = Target {
+                os,
+                arch,
+                libc,
+                ..target.clone()
+            };
+            download_only(client, args, &target, release).await
+        })
+        .buffered(get_jobs(args))
+        .collect()
+        .await;
+
+    let mut failures = Vec::new();
+    for (spec, result) in args.targets.iter().zip(results) {
+        if let Err(e) = result {
+            status!("Failed to download {spec}: {e:#}");
+            failures.push(spec.as_str());
+        }
+    }
+
+    if !failures.is_empty() {
+        bail!(
+            "{} of the targets failed to download: {}",
+            failures.len(),
+            failures.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn get_jobs(args: &Args) -> usize {
+    // Zero concurrent jobs would never make progress
+    args.jobs.map_or(DEFAULT_JOBS, |jobs| jobs as usize).max(1)
+}
+
+fn parse_target(spec: &str) -> Result<(String, String)> {
+    match spec.trim().split_once('/') {
+        Some((os, arch)) if !os.is_empty() && !arch.is_empty() => {
+            Ok((get_os(os).to_owned(), get_arch(arch).to_owned()))
+        }
+        _ => bail!("invalid target {spec}, expected os/arch"),
+    }
+}
+
+fn get_prune_candidates(cache_dir: &Path) -> Result<Vec<PathBuf>> {
+    if !cache_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut candidates = Vec::new();
+    for entry in fs::read_dir(cache_dir)
+        .with_context(|| format!("failed to read cache directory {cache_dir:?}"))?
+    {
+        let path = entry?.path();
+        let is_cache_file = path.extension().is_some_and(|ext| ext == "zip")
+            || path
+                .file_name()
+                .is_some_and(|name| name == CACHE_MANIFEST_FILE_NAME);
+        if is_cache_file && path.is_file() {
+            candidates.push(path);
+        }
+    }
+
+    let binary_cache_dir = cache_dir.join(BINARY_CACHE_DIR_NAME);
+    if binary_cache_dir.is_dir() {
+        for entry in fs::read_dir(&binary_cache_dir)
+            .with_context(|| format!("failed to read cache directory {binary_cache_dir:?}"))?
+        {
+            let path = entry?.path();
+            if path.is_file() {
+                candidates.push(path);
+            }
+        }
+    }
+    candidates.sort();
+
+    Ok(candidates)
+}
+
+fn export_cache(cache_dir: &Path, tar_path: &Path) -> Result<()> {
+    let candidates = get_prune_candidates(cache_dir)?;
+    if candidates.is_empty() {
+        bail!("cache directory {cache_dir:?} has no archives to export");
+    }
+
+    let mut tarball = io::BufWriter::new(
+        File::create(tar_path).with_context(|| format!("failed to create {tar_path:?}"))?,
+    );
+    for path in &candidates {
+        let name = path
+            .strip_prefix(cache_dir)
+            .unwrap_or(path)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let contents = fs::read(path).with_context(|| format!("failed to read {path:?}"))?;
+        write_tar_entry(&mut tarball, &name, &contents)
+            .with_context(|| format!("failed to write {name} to {tar_path:?}"))?;
+    }
+    // A tarball ends with two empty blocks
+    tarball
+        .write_all(&[0; TAR_BLOCK_SIZE * 2])
+        .and_then(|_| tarball.flush())
+        .with_context(|| format!("failed to write {tar_path:?}"))?;
+
+    status!("Exported {} cached files to {tar_path:?}", candidates.len());
+    Ok(())
+}
+
+/// Writes a regular file to a ustar tarball.
+fn write_tar_entry(out: &mut impl Write, name: &str, contents: &[u8]) -> io::Result<()> {
+    if name.len() >= 100 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{name} is too long for a tar entry"),
+        ));
+    }
+    let mtime = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let mut header = [0; TAR_BLOCK_SIZE];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[100..107].copy_from_slice(b"0000644");
+    header[108..115].copy_from_slice(b"0000000");
+    header[116..123].copy_from_slice(b"0000000");
+    header[124..135].copy_from_slice(format!("{:011o}", contents.len()).as_bytes());
+    header[136..147].copy_from_slice(format!("{mtime:011o}").as_bytes());
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    // The checksum is computed with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum: u32 = header.iter().map(|&b| u32::from(b)).sum();
+    header[148..155].copy_from_slice(format!("{checksum:06o}\0").as_bytes());
+
+    out.write_all(&header)?;
+    out.write_all(contents)?;
+    let padding = contents.len().next_multiple_of(TAR_BLOCK_SIZE) - contents.len();
+    out.write_all(&vec![0; padding])
+}
+
+/// Reads the regular files from a tarball, leaving out directories and other entry types.
+fn read_tar_entries(buffer: &[u8]) -> Result<Vec<(String, &[u8])>> {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    loop {
+        let Some(header) = buffer.get(offset..offset + TAR_BLOCK_SIZE) else {
+            bail!("tarball is truncated");
+        };
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+
+        let mut checksum_header = header.to_vec();
+        checksum_header[148..156].fill(b' ');
+        let checksum: u64 = checksum_header.iter().map(|&b| u64::from(b)).sum();
+        if parse_tar_number(&header[148..156]) != Some(checksum) {
+            bail!("not a tarball, or the tarball is corrupt");
+        }
+
+        let name = parse_tar_string(&header[..100]);
+        let prefix = parse_tar_string(&header[345..500]);
+        let name = match prefix.is_empty() {
+            true => name,
+            false => format!("{prefix}/{name}"),
+        };
+        let Some(size) = parse_tar_number(&header[124..136]).and_then(|s| usize::try_from(s).ok())
+        else {
+            bail!("invalid size for {name} in tarball");
+        };
+
+        offset += TAR_BLOCK_SIZE;
+        let Some(contents) = buffer.get(offset..offset + size) else {
+            bail!("tarball is truncated");
+        };
+        offset += size.next_multiple_of(TAR_BLOCK_SIZE);
+        if matches!(header[156], b'0' | 0) {
+            entries.push((name, contents));
+        }
+    }
+
+    Ok(entries)
+}
+
+fn parse_tar_string(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn parse_tar_number(field: &[u8]) -> Option<u64> {
+    let digits = parse_tar_string(field);
+    u64::from_str_radix(digits.trim_matches(' '), 8).ok()
+}
+
+/// Unpacks archives and cached binaries from a tarball into the cache directory. Archives are
+/// checked against the manifest in the tarball before anything is written.
+fn import_cache(args: &Args, cache_dir: &Path, tar_path: &Path) -> Result<()> {
+    let buffer = fs::read(tar_path).with_context(|| format!("failed to read {tar_path:?}"))?;
+    let entries =
+        read_tar_entries(&buffer).with_context(|| format!("failed to read {tar_path:?}"))?;
+
+    let manifest = entries
+        .iter()
+        .find(|(name, _)| name.trim_start_matches("./") == CACHE_MANIFEST_FILE_NAME)
+        .map(|(_, contents)| String::from_utf8_lossy(contents).into_owned());
+    let mut files = Vec::new();
+    for (name, contents) in &entries {
+        let name = name.trim_start_matches("./");
+        let parts: Vec<_> = name.split('/').collect();
+        let is_safe = parts
+            .iter()
+            .all(|p| !p.is_empty() && *p != "." && *p != "..");
+        match parts.as_slice() {
+            [zip_name] if is_safe && zip_name.ends_with(".zip") => {
+                let expected = manifest
+                    .as_deref()
+                    .and_then(|m| find_checksum(m, zip_name))
+                    .map(Checksum::from_hex)
+                    .transpose()?;
+                match expected {
+                    Some(checksum) if !checksum.verify(contents) => bail!(
+                        "checksum of {zip_name} does not match the manifest in {tar_path:?}, nothing was imported"
+                    ),
+                    Some(_) => {}
+                    None => status!("Warning: {zip_name} has no checksum in {tar_path:?}, importing it unverified"),
+                }
+                files.push((cache_dir.join(zip_name), *contents, name));
+            }
+            [BINARY_CACHE_DIR_NAME, binary_name] if is_safe => {
+                let expected = manifest
+                    .as_deref()
+                    .and_then(|m| find_checksum(m, name))
+                    .map(Checksum::from_hex)
+                    .transpose()?;
+                match expected {
+                    Some(checksum) if !checksum.verify(contents) => bail!(
+                        "checksum of {name} does not match the manifest in {tar_path:?}, nothing was imported"
+                    ),
+                    Some(_) => {
+                        let path = cache_dir.join(BINARY_CACHE_DIR_NAME).join(binary_name);
+                        files.push((path, *contents, name));
+                    }
+                    None => status!("Skipping {name}, which has no checksum in {tar_path:?}"),
+                }
+            }
+            [CACHE_MANIFEST_FILE_NAME] => {}
+            _ => status!("Skipping {name}, which is not part of the cache layout"),
+        }
+    }
+
+    let overwritten = files.iter().filter(|(path, _, _)| path.exists()).count();
+    if overwritten > 0 {
+        confirm(
+            args,
+            &format!("Overwrite {overwritten} files already in {cache_dir:?}?"),
+        )?;
+    }
+
+    fs::create_dir_all(cache_dir.join(BINARY_CACHE_DIR_NAME))
+        .with_context(|| format!("failed to create cache directory {cache_dir:?}"))?;
+    for (path, contents, manifest_name) in &files {
+        write_atomically(path, contents).with_context(|| format!("failed to write {path:?}"))?;
+        update_cache_manifest(cache_dir, manifest_name, contents)?;
+    }
+
+    status!("Imported {} cached files into {cache_dir:?}", files.len());
+    Ok(())
+}
+
+/// Collects the versioned binary of a release, a symlink pointing at it and its cached archive.
+/// Binaries installed with the overwrite mode are left alone, as they are not tied to a version.
+fn get_uninstall_candidates(
+    args: &Args,
+    target: &Target,
+    program_path: &Path,
+    release: &ReleaseInfo,
+) -> Result<Vec<PathBuf>> {
+    let install_path = match &args.install_path_template {
+        Some(template) => expand_install_path_template(
+            template,
+            args.get_program_name(),
+            &release.raw_version,
+            target,
+        )?,
+        None => get_install_path(InstallMode::Versioned, program_path, &release.raw_version),
+    };
+
+    let mut candidates = Vec::new();
+    if install_path.is_file() {
+        let is_linked = fs::symlink_metadata(program_path).is_ok_and(|m| m.is_symlink())
+            && fs::canonicalize(program_path).ok() == fs::canonicalize(&install_path).ok();
+        if is_linked {
+            candidates.push(program_path.to_path_buf());
+        }
+        candidates.push(install_path);
+    }
+
+    if let Some(cache_dir) = get_cache_dir(args) {
+        let zip_path = cache_dir.join(release.get_zip_name(target));
+        if zip_path.is_file() {
+            candidates.push(zip_path);
+        }
+    }
+
+    if let Some(binary_path) = get_binary_cache_path(args, target, &release.raw_version) {
+        if binary_path.is_file() {
+            candidates.push(binary_path);
+        }
+    }
+
+    Ok(candidates)
+}
+
+fn remove_files(paths: &[PathBuf], dry_run: bool) -> Result<()> {
+    let mut freed = 0;
+    for path in paths {
+        let metadata = fs::symlink_metadata(path)
+            .with_context(|| format!("failed to read metadata of {path:?}"))?;
+        freed += metadata.len();
+
+        match dry_run {
+            true => status!("Would remove {path:?}"),
+            false => {
+                fs::remove_file(path).with_context(|| format!("failed to remove {path:?}"))?;
+                status!("Removed {path:?}");
+            }
+        }
+    }
+
+    match dry_run {
+        true => status!("Would free {}", format_size(freed)),
+        false => status!("Freed {}", format_size(freed)),
+    }
+    Ok(())
+}
+
+/// Fails early when the filesystem holding `dir`, or its nearest existing parent, has less than
+/// `needed` bytes available, rather than partway through writing.
+fn check_disk_space(dir: &Path, needed: u64) -> Result<()> {
+    let Some(existing) = dir.ancestors().find(|p| p.exists()) else {
+        return Ok(());
+    };
+    let Some(available) = get_available_space(existing) else {
+        return Ok(());
+    };
+
+    if available < needed {
+        bail!(
+            "insufficient disk space in {dir:?}: {} needed, {} available",
+            format_size(needed),
+            format_size(available)
+        );
+    }
+    Ok(())
+}
+
+// The field widths of statvfs differ between platforms
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+fn get_available_space(path: &Path) -> Option<u64> {
+    use std::{ffi::CString, mem::MaybeUninit, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: path is a valid C string and statvfs only writes to stat
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    // SAFETY: statvfs succeeded, so stat is initialised
+    let stat = unsafe { stat.assume_init() };
+    Some((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+}
+
+#[cfg(not(unix))]
+fn get_available_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{size:.1} {}", UNITS[unit]),
+    }
+}
+
+fn reinstall_from_cache(
+    args: &Args,
+    target: &Target,
+    program_path: &Path,
+    release: ReleaseInfo,
+) -> Result<PathBuf> {
+    check_min_version(args.min_version.as_deref(), &release)?;
+    check_policy(args.policy_constraint.as_deref(), &release)?;
+    let zip_name = release.get_zip_name(target);
+    match get_cached_archive(args, &zip_name, None)? {
+        Some(archive) => install_archive(args, program_path, &release.raw_version, archive),
+        None => bail!(
+            "{:?} {} is not cached, run without --reinstall to download it",
+            release.program_name,
+            release.raw_version
+        ),
+    }
+}
+
+/// Returns the distinct versions of a batch that the installs will accept, leaving out the ones
+/// that are invalid or fail the minimum version or policy checks.
+fn get_prefetch_releases(args: &Args, entries: &[(String, PathBuf)]) -> Vec<ReleaseInfo> {
+    let mut versions: Vec<&String> = entries.iter().map(|(version, _)| version).collect();
+    versions.sort();
+    versions.dedup();
+    versions
+        .into_iter()
+        .filter_map(|version| ReleaseInfo::new(args.get_program_name(), version.clone()).ok())
+        .filter(|release| {
+            check_min_version(args.min_version.as_deref(), release).is_ok()
+                && check_policy(args.policy_constraint.as_deref(), release).is_ok()
+        })
+        .collect()
+}
+
+async fn install_batch(
+    client: &Client,
+    args: &Args,
+    target: &Target,
+    batch_path: &Path,
+) -> Result<()> {
+    let contents = fs::read_to_string(batch_path)
+        .with_context(|| format!("failed to read batch file at {batch_path:?}"))?;
+
+    let entries = parse_batch_file(&contents)?;
+
+    // Download each version into the cache first, so entries sharing a version only fetch it once
+    // and the installs below read from the cache. Without a cache to hold them the installs would
+    // download everything again, so nothing is prefetched. Failures are reported by the installs.
+    let is_cache_usable = get_cache_dir(args).is_some_and(|dir| fs::create_dir_all(dir).is_ok());
+    if is_cache_usable {
+        stream::iter(get_prefetch_releases(args, &entries))
+            .map(|release| async move { get_zip(client, args, target, &release).await })
+            .buffer_unordered(get_jobs(args))
+            .for_each(|_| async {})
+            .await;
+    }
+
+    let mut failures = Vec::new();
+    for (version, program_path) in entries {
+        let release = ReleaseInfo::new(args.get_program_name(), version.clone());
+        let result = async { install_version(client, args, target, &program_path, release?).await };
+        if let Err(e) = result.await {
+            status!("Failed to install {version} to {program_path:?}: {e:#}");
+            failures.push(format!("{version} -> {}", program_path.display()));
+        }
+    }
+
+    if !failures.is_empty() {
+        bail!(
+            "{} of the batch entries failed to install: {}",
+            failures.len(),
+            failures.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn parse_batch_file(contents: &str) -> Result<Vec<(String, PathBuf)>> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| match line.split_once('=') {
+            Some((version, path)) if !version.trim().is_empty() && !path.trim().is_empty() => {
+                Ok((version.trim().to_string(), PathBuf::from(path.trim())))
+            }
+            _ => bail!("invalid batch entry on line {number}, expected version=path"),
+        })
+        .collect()
+}
+
+fn install_from_zip(args: &Args, program_path: &Path, zip_path: &Path) -> Result<PathBuf> {
+    let buffer = fs::read(zip_path).map_err(|source| TfswitcherError::Io {
+        message: format!("failed to read archive at {zip_path:?}"),
+        source,
+    })?;
+
+    if let Some(expected) = &args.checksum {
+        if !Checksum::from_hex(expected)?.verify(&buffer) {
+            bail!("checksum mismatch for archive at {zip_path:?}");
+        }
+        status!("Verified checksum of {zip_path:?}");
+    }
+
+    let mut archive =
+        ZipArchive::new(Cursor::new(buffer)).map_err(|source| TfswitcherError::ArchiveCorrupt {
+            message: format!("failed to read {zip_path:?} as ZIP archive"),
+            source,
+        })?;
+    check_single_executable(args, &mut archive)
+        .with_context(|| format!("invalid archive at {zip_path:?}"))?;
+
+    let version = zip_path
+        .file_name()
+        .and_then(|name| {
+            get_version_from_zip_name(
+                &args.get_program_name().to_string(),
+                &name.to_string_lossy(),
+            )
+        })
+        .or_else(|| args.install_version.clone());
+    let install_mode = args.install_mode.unwrap_or_default();
+    match version {
+        Some(version) => {
+            let release = ReleaseInfo::new(args.get_program_name(), version.clone())?;
+            check_min_version(args.min_version.as_deref(), &release)?;
+            check_policy(args.policy_constraint.as_deref(), &release)?;
+            install_archive(args, program_path, &version, archive)
+        }
+        None if install_mode == InstallMode::Overwrite => {
+            install_archive(args, program_path, "local", archive)
+        }
+        None => bail!("could not determine version of archive at {zip_path:?}, please specify it"),
+    }
+}
+
+/// Local archives are only installed when the program is their one executable entry, as they
+/// haven't come from a known release. Other executables are allowed with --extract-all.
+fn check_single_executable(args: &Args, archive: &mut ZipArchive<Cursor<Vec<u8>>>) -> Result<()> {
+    let entry_name = args.get_entry_name();
+    let os = Target::from_args(args).os;
+    let mut executables = Vec::new();
+    for index in 0..archive.len() {
+        let mut file = archive
+            .by_index(index)
+            .with_context(|| "could not get item in archive")?;
+        if file.is_dir() {
+            continue;
+        }
+
+        let mut header = Vec::new();
+        file.by_ref()
+            .take(4)
+            .read_to_end(&mut header)
+            .with_context(|| format!("failed to read {} from archive", file.name()))?;
+        if detect_executable_format(&header).is_none() {
+            continue;
+        }
+        if file.name() == entry_name {
+            check_executable_format(&header, &os)?;
+            if file.unix_mode().is_some_and(|mode| mode & 0o111 == 0) {
+                bail!("{entry_name} is not marked as executable");
+            }
+        }
+        executables.push(file.name().to_owned());
+    }
+
+    if !executables.contains(&entry_name) {
+        bail!("archive does not contain an executable named {entry_name}");
+    }
+    if executables.len() > 1 && !args.extract_all {
+        bail!(
+            "archive contains more than one executable ({}), pass --extract-all to install them all",
+            executables.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Collects the versions with an archive for the target in any of the cache directories,
+/// sorted newest first.
+fn get_cached_versions(
+    cache_dirs: &[&Path],
+    args: &Args,
+    target: &Target,
+) -> Result<Vec<ReleaseInfo>> {
+    let program_name = args.get_program_name().to_string();
+    let mut versions = Vec::new();
+    for cache_dir in cache_dirs.iter().filter(|dir| dir.is_dir()) {
+        for entry in fs::read_dir(cache_dir)
+            .with_context(|| format!("failed to read cache directory {cache_dir:?}"))?
+        {
+            let zip_name = entry?.file_name().to_string_lossy().into_owned();
+            let Some(version) = get_version_from_zip_name(&program_name, &zip_name) else {
+                continue;
+            };
+            let std::result::Result::Ok(release) =
+                ReleaseInfo::new(args.get_program_name(), version)
+            else {
+                continue;
+            };
+            let is_wanted = release.get_zip_name(target) == zip_name
+                && (args.list_all || !release.is_prerelease())
+                && !versions.contains(&release);
+            if is_wanted {
+                versions.push(release);
+            }
+        }
+    }
+    sort_versions(&mut versions);
+
+    Ok(versions)
+}
+
+fn get_version_from_zip_name(program_name: &str, zip_name: &str) -> Option<String> {
+    let re = Regex::new(&format!(
+        r"^{}_(?<version>.+)_[a-z0-9]+_[a-z0-9]+\.zip$",
+        regex::escape(program_name)
+    ))
+    .expect("Invalid regex");
+
+    re.captures(zip_name)
+        .and_then(|c| c.name("version"))
+        .map(|v| v.as_str().to_owned())
+}
+
+fn install_archive(
+    args: &Args,
+    program_path: &Path,
+    version: &str,
+    mut archive: ZipArchive<Cursor<Vec<u8>>>,
+) -> Result<PathBuf> {
+    let install_path = get_resolved_install_path(args, program_path, version)?;
+    Event::Extracting {
+        path: &install_path,
+    }
+    .emit(args.events);
+
+    let mode = args.mode.unwrap_or(DEFAULT_MODE);
+    let os = Target::from_args(args).os;
+    let entry_name = args.get_entry_name();
+    extract_zip_archive(&entry_name, &install_path, mode, &os, &mut archive)?;
+
+    if args.extract_all {
+        let install_dir = install_path.parent().unwrap_or(Path::new("."));
+        extract_other_executables(&mut archive, &entry_name, install_dir, mode, &os)?;
+    }
+
+    if args.cache_binaries {
+        cache_extracted_binary(args, version, &install_path);
+    }
+
+    finish_install(args, version, &install_path, program_path)
+}
+
+/// Copies a previously extracted binary into place instead of extracting the archive again.
+fn install_cached_binary(
+    args: &Args,
+    program_path: &Path,
+    version: &str,
+    cached_binary: &[u8],
+) -> Result<PathBuf> {
+    let install_path = get_resolved_install_path(args, program_path, version)?;
+    status!("Copying cached binary to {install_path:?}");
+
+    let mut outfile = create_output_file(&install_path, args.mode.unwrap_or(DEFAULT_MODE))?;
+    outfile
+        .write_all(cached_binary)
+        .with_context(|| format!("failed to copy cached binary to {install_path:?}"))?;
+
+    finish_install(args, version, &install_path, program_path)
+}
+
+/// Links the binary into place if needed and returns the path the binary ended up at.
+fn finish_install(
+    args: &Args,
+    version: &str,
+    install_path: &Path,
+    program_path: &Path,
+) -> Result<PathBuf> {
+    // With symlinks, the link is what ends up on $PATH
+    let installed_path = match args.install_mode.unwrap_or_default() {
+        InstallMode::Symlink => {
+            create_symlink(install_path, program_path)?;
+            program_path
+        }
+        _ => install_path,
+    };
+
+    let program_name = args.get_program_name();
+    let changelog_url = match args.show_changelog_url {
+        true => Some(program_name.get_changelog_url(version)),
+        false => None,
+    };
+    if let (Some(url), OutputFormat::Text) = (&changelog_url, args.output) {
+        status!("Release notes: {url}");
+    }
+    let summary = format_install_summary(
+        args.output,
+        &program_name,
+        version,
+        installed_path,
+        changelog_url.as_deref(),
+    );
+    match args.exec {
+        true => status!("{summary}"),
+        false => println!("{summary}"),
+    }
+    Ok(installed_path.to_path_buf())
+}
+
+/// Formats the line reporting the outcome of an install, as the last line of the output.
+fn format_install_summary(
+    output: OutputFormat,
+    program_name: &ProgramName,
+    version: &str,
+    path: &Path,
+    changelog_url: Option<&str>,
+) -> String {
+    match output {
+        OutputFormat::Text => format!("Installed {program_name} {version} -> {}", path.display()),
+        OutputFormat::Json => {
+            let mut summary = serde_json::json!({
+                "program": program_name.to_string(),
+                "version": version,
+                "path": path,
+            });
+            if let Some(url) = changelog_url {
+                summary["changelog_url"] = url.into();
+            }
+            summary.to_string()
+        }
+    }
+}
+
+fn get_resolved_install_path(args: &Args, program_path: &Path, version: &str) -> Result<PathBuf> {
+    let install_mode = args.install_mode.unwrap_or_default();
+    let install_path = match &args.install_path_template {
+        Some(template) => {
+            let target = Target::from_args(args);
+            let path =
+                expand_install_path_template(template, args.get_program_name(), version, &target)?;
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("failed to create directory {parent:?}"))?;
+            }
+            path
+        }
+        None => get_install_path(install_mode, program_path, version),
+    };
+    let install_path = resolve_install_symlink(&install_path, args.follow_symlink)?;
+    status!(
+        "{:?} {version} will be installed to {install_path:?}",
+        args.get_program_name(),
+    );
+
+    Ok(install_path)
+}
+
+fn expand_install_path_template(
+    template: &str,
+    program_name: ProgramName,
+    version: &str,
+    target: &Target,
+) -> Result<PathBuf> {
+    let path = expand_template(
+        template,
+        &[
+            ("product", &program_name.to_string()),
+            ("version", version),
+            ("os", &target.os),
+            ("arch", &target.arch),
+        ],
+    );
+
+    expand_path(Path::new(&path))
+}
+
+/// Writing to a symlink would replace whatever it points to, which is often managed by another
+/// version manager, so this is only done when explicitly asked for.
+fn resolve_install_symlink(install_path: &Path, follow_symlink: bool) -> Result<PathBuf> {
+    let is_symlink = fs::symlink_metadata(install_path).is_ok_and(|m| m.file_type().is_symlink());
+    if !is_symlink {
+        return Ok(install_path.to_path_buf());
+    }
+
+    let target = fs::canonicalize(install_path)
+        .with_context(|| format!("failed to resolve symlink at {install_path:?}"))?;
+    if !follow_symlink {
+        bail!(
+            "{install_path:?} is a symlink to {target:?}, pass --follow-symlink to replace the target"
+        );
+    }
+
+    status!("{install_path:?} is a symlink, installing to {target:?}");
+    Ok(target)
+}
+
+fn get_install_path(install_mode: InstallMode, program_path: &Path, version: &str) -> PathBuf {
+    match install_mode {
+        InstallMode::Overwrite => program_path.to_path_buf(),
+        InstallMode::Versioned | InstallMode::Symlink => {
+            let mut file_name = program_path
+                .file_stem()
+                .unwrap_or(program_path.as_os_str())
+                .to_owned();
+            file_name.push(format!("_{version}"));
+            if let Some(extension) = program_path.extension() {
+                file_name.push(".");
+                file_name.push(extension);
+            }
+            program_path.with_file_name(file_name)
+        }
+    }
+}
+
+fn create_symlink(target: &Path, link: &Path) -> Result<()> {
+    if fs::symlink_metadata(link).is_ok() {
+        fs::remove_file(link)
+            .with_context(|| format!("failed to remove existing binary at {link:?}"))?;
+    }
+
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(target, link)
+        .with_context(|| format!("failed to create symlink at {link:?}"))?;
+    #[cfg(windows)]
+    std::os::windows::fs::symlink_file(target, link)
+        .with_context(|| format!("failed to create symlink at {link:?}"))?;
+
+    status!("Linked {link:?} to {target:?}");
+    Ok(())
+}
+
+async fn get_zip(
+    client: &Client,
+    args: &Args,
+    target: &Target,
+    release: &ReleaseInfo,
+) -> Result<ZipArchive<Cursor<Vec<u8>>>> {
+    let checksum = match args.verify || args.cosign {
+        true => get_optional_checksum(client, args, target, release).await?,
+        false => None,
+    };
+
+    let zip_name = release.get_zip_name(target);
+    if let Some(archive) = get_cached_archive(args, &zip_name, checksum.as_ref())? {
+        return Ok(archive);
+    }
+
+    download_and_save_zip(client, args, target, release, checksum.as_ref()).await
+}
+
+/// Looks up an archive in the shared cache, then in the per-user cache.
+fn get_cached_archive(
+    args: &Args,
+    zip_name: &str,
+    checksum: Option<&Checksum>,
+) -> Result<Option<ZipArchive<Cursor<Vec<u8>>>>> {
+    let shared_archive = get_shared_cached_zip(
+        get_shared_cache_dir().as_deref(),
+        zip_name,
+        checksum,
+        args.strict,
+    )?;
+    if shared_archive.is_some() {
+        return Ok(shared_archive);
+    }
+
+    get_valid_cached_zip(
+        get_cache_dir(args).as_deref(),
+        zip_name,
+        checksum,
+        args.strict,
+    )
+}
+
+/// Returns the cached archive if it is present and intact. Without a published checksum, the
+/// archive is checked against the checksum recorded in the cache manifest when it was cached. A
+/// cached archive that fails its checksum or cannot be read as a ZIP archive is removed so that it
+/// can be downloaded again.
+fn get_valid_cached_zip(
+    cache_dir: Option<&Path>,
+    zip_name: &str,
+    checksum: Option<&Checksum>,
+    strict: bool,
+) -> Result<Option<ZipArchive<Cursor<Vec<u8>>>>> {
+    let Some(cursor) = get_cached_zip(cache_dir, zip_name)? else {
+        return Ok(None);
+    };
+
+    let manifest_checksum = cache_dir.and_then(|dir| get_manifest_checksum(dir, zip_name));
+    if let Some(expected) = checksum.or(manifest_checksum.as_ref()) {
+        if !expected.verify(cursor.get_ref()) {
+            if strict {
+                bail!("checksum mismatch for cached archive {zip_name}");
+            }
+            status!("Checksum mismatch for cached archive, removing it and re-downloading");
+            remove_cached_zip(cache_dir, zip_name)?;
+            return Ok(None);
+        }
+    }
+
+    match ZipArchive::new(cursor) {
+        Err(e) => {
+            status!("Cached archive is corrupt ({e}), removing it and re-downloading");
+            remove_cached_zip(cache_dir, zip_name)?;
+            Ok(None)
+        }
+        archive => Ok(Some(archive?)),
+    }
+}
+
+/// Returns the archive from the shared cache if it is present and intact. The shared cache is
+/// usually not writable, so a bad archive is skipped instead of removed.
+fn get_shared_cached_zip(
+    cache_dir: Option<&Path>,
+    zip_name: &str,
+    checksum: Option<&Checksum>,
+    strict: bool,
+) -> Result<Option<ZipArchive<Cursor<Vec<u8>>>>> {
+    let Some(cursor) = get_cached_zip(cache_dir, zip_name)? else {
+        return Ok(None);
+    };
+
+    let manifest_checksum = cache_dir.and_then(|dir| get_manifest_checksum(dir, zip_name));
+    if checksum
+        .or(manifest_checksum.as_ref())
+        .is_some_and(|expected| !expected.verify(cursor.get_ref()))
+    {
+        if strict {
+            bail!("checksum mismatch for shared cached archive {zip_name}");
+        }
+        status!("Checksum mismatch for shared cached archive, ignoring it");
+        return Ok(None);
+    }
+
+    match ZipArchive::new(cursor) {
+        Err(e) => {
+            status!("Shared cached archive is corrupt ({e}), ignoring it");
+            Ok(None)
+        }
+        archive => Ok(Some(archive?)),
+    }
+}
+
+/// Some old releases were published without a checksums file, so a missing one only skips
+/// verification, unless checksums are required or the signature has to be verified.
+async fn get_optional_checksum(
+    client: &Client,
+    args: &Args,
+    target: &Target,
+    release: &ReleaseInfo,
+) -> Result<Option<Checksum>> {
+    match get_checksum(client, args, target, release).await {
+        Err(e) if is_not_found(&e) && !args.require_checksum && !args.cosign => {
+            status!(
+                "Warning: no {} published for {:?} {}, installing without verification",
+                release.get_checksums_name(),
+                release.program_name,
+                release.raw_version
+            );
+            Ok(None)
+        }
+        checksum => checksum.map(Some),
+    }
+}
+
+async fn get_checksum(
+    client: &Client,
+    args: &Args,
+    target: &Target,
+    release: &ReleaseInfo,
+) -> Result<Checksum> {
+    let url = release.get_checksums_url(target);
+    status!("Downloading checksums from {url}");
+    let contents = get_http(client, &url, &args.retry_on)
+        .await?
+        .text()
+        .await
+        .with_context(|| "failed to read checksums")?;
+
+    if args.cosign {
+        verify_checksums_signature(client, target, release, &contents, &args.retry_on).await?;
+    }
+
+    let zip_name = release.get_zip_name(target);
+    match find_checksum(&contents, &zip_name) {
+        Some(checksum) => Checksum::from_hex(checksum),
+        None => bail!(
+            "could not find checksum for {zip_name} in {}",
+            release.get_checksums_name()
+        ),
+    }
+}
+
+/// Verifies the keyless cosign signature OpenTofu publishes for its checksums file, so that the
+/// checksums and in turn the archive are known to come from the OpenTofu release workflow.
+async fn verify_checksums_signature(
+    client: &Client,
+    target: &Target,
+    release: &ReleaseInfo,
+    checksums: &str,
+    retry_on: &[u16],
+) -> Result<()> {
+    if release.program_name != ProgramName::OpenTofu {
+        bail!("cosign verification is only supported for OpenTofu");
+    }
+
+    let checksums_name = release.get_checksums_name();
+    let tmp_dir =
+        TempDir::new(APP_DIR_NAME).with_context(|| "failed to create temporary directory")?;
+    let checksums_path = tmp_dir.path().join(&checksums_name);
+    fs::write(&checksums_path, checksums)?;
+
+    let mut bundle_paths = Vec::new();
+    for extension in ["sig", "pem"] {
+        let file_name = format!("{checksums_name}.{extension}");
+        let contents = get_http(
+            client,
+            &release.get_release_file_url(target, &file_name),
+            retry_on,
+        )
+        .await?
+        .bytes()
+        .await
+        .with_context(|| format!("failed to read {file_name}"))?;
+        let path = tmp_dir.path().join(&file_name);
+        fs::write(&path, contents)?;
+        bundle_paths.push(path);
+    }
+
+    let output = Command::new("cosign")
+        .arg("verify-blob")
+        .arg("--certificate-identity")
+        .arg(get_opentofu_signing_identity(&release.version))
+        .arg("--certificate-oidc-issuer")
+        .arg(OPENTOFU_SIGNING_ISSUER)
+        .arg("--signature")
+        .arg(&bundle_paths[0])
+        .arg("--certificate")
+        .arg(&bundle_paths[1])
+        .arg(&checksums_path)
+        .output()
+        .with_context(|| "failed to run cosign, make sure it is installed")?;
+    if !output.status.success() {
+        bail!(
+            "cosign could not verify {checksums_name}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    status!("Verified signature of {checksums_name}");
+    Ok(())
+}
+
+/// OpenTofu releases are signed by the release workflow running on the branch of their minor
+/// version.
+fn get_opentofu_signing_identity(version: &Version) -> String {
+    format!(
+        "https://github.com/opentofu/opentofu/.github/workflows/release.yml@refs/heads/v{}.{}",
+        version.major, version.minor
+    )
+}
+
+/// Finds the checksum of the line whose file name column is exactly `zip_name`, so that e.g. an
+/// `amd64` archive can never match the line of an `arm64` one.
+fn find_checksum<'a>(contents: &'a str, zip_name: &str) -> Option<&'a str> {
+    contents.lines().find_map(|line| {
+        let (checksum, file_name) = line.split_once(char::is_whitespace)?;
+        // sha256sum marks files hashed in binary mode with a leading `*`
+        let file_name = file_name.trim();
+        let file_name = file_name.strip_prefix('*').unwrap_or(file_name);
+        (file_name == zip_name).then_some(checksum)
+    })
+}
+
+fn get_os(os: &str) -> &str {
+    match os {
+        "macos" => "darwin",
+        "illumos" => "solaris",
+        _ => os,
+    }
+}
+
+fn is_supported_platform(os: &str, arch: &str) -> bool {
+    SUPPORTED_PLATFORMS
+        .iter()
+        .any(|(supported_os, arches)| *supported_os == os && arches.contains(&arch))
+}
+
+fn is_not_found(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|e| e.downcast_ref::<reqwest::Error>())
+        .any(|e| e.status() == Some(reqwest::StatusCode::NOT_FOUND))
+}
+
+fn get_arch(arch: &str) -> &str {
+    match arch {
+        "x86" => "386",
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        _ => arch,
+    }
+}
+
+fn get_cached_zip(cache_dir: Option<&Path>, zip_name: &str) -> Result<Option<Cursor<Vec<u8>>>> {
+    match cache_dir {
+        Some(dir) => {
+            let path = dir.join(zip_name);
+            if !path.exists() {
+                return Ok(None);
+            }
+
+            status!("Using cached archive at {path:?}");
+            let buffer = fs::read(&path).map_err(|source| TfswitcherError::Io {
+                message: format!("failed to read cached archive at {path:?}"),
+                source,
+            })?;
+            let cursor = Cursor::new(buffer);
+
+            Ok(Some(cursor))
+        }
+        None => Ok(None),
+    }
+}
+
+fn remove_cached_zip(cache_dir: Option<&Path>, zip_name: &str) -> Result<()> {
+    if let Some(dir) = cache_dir {
+        let path = dir.join(zip_name);
+        fs::remove_file(&path).map_err(|source| TfswitcherError::Io {
+            message: format!("failed to remove cached archive at {path:?}"),
+            source,
+        })?;
+    }
+
+    Ok(())
+}
+
+async fn download_and_save_zip(
+    client: &Client,
+    args: &Args,
+    target: &Target,
+    release: &ReleaseInfo,
+    checksum: Option<&Checksum>,
+) -> Result<ZipArchive<Cursor<Vec<u8>>>> {
+    if !is_supported_platform(&target.os, &target.arch) {
+        status!("Warning: {target} is not a platform that releases are usually published for");
+    }
+
+    // CDNs occasionally serve a truncated body with a successful status, so an incomplete
+    // archive is downloaded once more before giving up
+    let mut retried = false;
+    let contents = loop {
+        match download_zip(client, args, target, release, checksum).await {
+            Err(e) if !retried && is_incomplete_download(&e) => {
+                status!("Downloaded archive is incomplete ({e:#}), retrying");
+                retried = true;
+            }
+            contents => break contents?,
+        }
+    };
+
+    save_zip_to_cache(args, &release.get_zip_name(target), &contents);
+
+    let archive = ZipArchive::new(Cursor::new(contents))
+        .with_context(|| "failed to read HTTP response as ZIP archive")?;
+    Ok(archive)
+}
+
+/// Downloads an archive and checks that it is complete, matches the checksum and can be read as a
+/// ZIP archive.
+async fn download_zip(
+    client: &Client,
+    args: &Args,
+    target: &Target,
+    release: &ReleaseInfo,
+    checksum: Option<&Checksum>,
+) -> Result<Vec<u8>> {
+    let url = release.get_download_url(target);
+    status!("Downloading archive from {url}");
+    let response = match get_http(client, &url, &args.retry_on).await {
+        Err(e) if is_not_found(&e) => {
+            return Err(e.context(format!(
+                "{:?} {} was not published for {target}",
+                release.program_name, release.raw_version
+            )))
+        }
+        response => response?,
+    };
+    let content_length = response.content_length();
+    if let (Some(cache_dir), Some(length)) = (get_cache_dir(args), content_length) {
+        check_disk_space(&cache_dir, length)?;
+    }
+    let contents = read_response_body(response, args.max_rate, args.events).await?;
+
+    if let Some(expected) = content_length.filter(|len| *len != contents.len() as u64) {
+        bail!(TfswitcherError::Truncated {
+            url,
+            expected,
+            received: contents.len() as u64,
+        });
+    }
+
+    let zip_name = release.get_zip_name(target);
+    if let Some(expected) = checksum {
+        Event::Verifying { file: &zip_name }.emit(args.events);
+        if !expected.verify(&contents) {
+            bail!("checksum mismatch for downloaded archive {zip_name}");
+        }
+        status!("Verified checksum of {zip_name}");
+    }
+
+    ZipArchive::new(Cursor::new(contents.as_slice())).map_err(|source| {
+        TfswitcherError::ArchiveCorrupt {
+            message: "failed to read HTTP response as ZIP archive".into(),
+            source,
+        }
+    })?;
+
+    Ok(contents)
+}
+
+fn is_incomplete_download(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref(),
+        Some(TfswitcherError::Truncated { .. } | TfswitcherError::ArchiveCorrupt { .. })
+    )
+}
+
+/// Reads the response body, sleeping between chunks so that all downloads together stay under
+/// `max_rate` bytes per second.
+async fn read_response_body(
+    mut response: Response,
+    max_rate: Option<u64>,
+    events: Option<EventFormat>,
+) -> Result<Vec<u8>> {
+    let total = response.content_length();
+    let mut contents = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .with_context(|| "failed to read HTTP response")?
+    {
+        contents.extend_from_slice(&chunk);
+        Event::Downloading {
+            bytes: contents.len() as u64,
+            total,
+        }
+        .emit(events);
+        if let Some(rate) = max_rate.filter(|rate| *rate > 0) {
+            let deadline = {
+                let mut next = THROTTLE_NEXT.lock().unwrap_or_else(|e| e.into_inner());
+                let deadline =
+                    get_throttle_deadline(*next, Instant::now(), chunk.len() as u64, rate);
+                *next = Some(deadline);
+                deadline
+            };
+            tokio::time::sleep_until(deadline.into()).await;
+        }
+    }
+
+    Ok(contents)
+}
+
+/// Reserves `bytes` of the shared budget and returns when the caller may continue, starting from
+/// the later of `next` (when the budget is next free) and `now`.
+fn get_throttle_deadline(
+    next: Option<Instant>,
+    now: Instant,
+    bytes: u64,
+    max_rate: u64,
+) -> Instant {
+    let start = next.filter(|next| *next > now).unwrap_or(now);
+    start + Duration::from_secs_f64(bytes as f64 / max_rate as f64)
+}
+
+/// Caches a downloaded archive in the shared cache if it is writable, otherwise in the per-user
+/// cache.
+fn save_zip_to_cache(args: &Args, zip_name: &str, buffer: &[u8]) {
+    if let Some(mut path) = get_shared_cache_dir() {
+        if cache_zip_archive(&mut path, zip_name, buffer).is_ok() {
+            status!("Cached archive to {path:?}");
+            return;
+        }
+    }
+
+    match get_cache_dir(args) {
+        Some(mut path) => {
+            status!("Caching archive to {path:?}");
+            if let Err(e) = cache_zip_archive(&mut path, zip_name, buffer) {
+                status!("Unable to cache archive: {e}");
+            };
+        }
+        None => status!("Unable to cache archive: could not find cache directory"),
+    }
+}
+
+fn cache_zip_archive(cache_location: &mut PathBuf, zip_name: &str, buffer: &[u8]) -> Result<()> {
+    fs::create_dir_all(&cache_location)?;
+    update_cache_manifest(cache_location, zip_name, buffer)?;
+    cache_location.push(zip_name);
+    write_atomically(cache_location, buffer)?;
+
+    Ok(())
+}
+
+/// Writes to a temporary file next to `path` and renames it into place, so that an interrupted
+/// write never leaves a partial file behind under the real name.
+fn write_atomically(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = path.with_file_name(format!("{TEMP_FILE_PREFIX}{}-{file_name}", process::id()));
+
+    let result = fs::write(&temp_path, contents).and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Removes temporary files left behind by interrupted runs. Only files with the temporary prefix
+/// that have not been touched for a while are removed, so writes in progress are left alone.
+fn remove_stale_temp_files(dir: &Path) {
+    let Some(entries) = fs::read_dir(dir).ok() else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let is_temp_file = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.starts_with(TEMP_FILE_PREFIX));
+        let is_stale = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > STALE_TEMP_FILE_AGE);
+        if is_temp_file && is_stale && fs::remove_file(entry.path()).is_ok() {
+            status!("Removed leftover temporary file {:?}", entry.path());
+        }
+    }
+}
+
+fn get_manifest_checksum(cache_dir: &Path, zip_name: &str) -> Option<Checksum> {
+    let contents = fs::read_to_string(cache_dir.join(CACHE_MANIFEST_FILE_NAME)).ok()?;
+    find_checksum(&contents, zip_name).and_then(|hex| Checksum::from_hex(hex).ok())
+}
+
+/// Records the SHA256 checksum of a cached archive in the cache manifest, replacing any previous
+/// entry for the same archive.
+fn update_cache_manifest(cache_dir: &Path, zip_name: &str, buffer: &[u8]) -> Result<()> {
+    let path = cache_dir.join(CACHE_MANIFEST_FILE_NAME);
+    let contents = match path.exists() {
+        true => fs::read_to_string(&path)
+            .with_context(|| format!("failed to read cache manifest at {path:?}"))?,
+        false => String::new(),
+    };
+
+    let mut manifest: String = contents
+        .lines()
+        .filter(|line| find_checksum(line, zip_name).is_none())
+        .map(|line| format!("{line}\n"))
+        .collect();
+    manifest.push_str(&format_checksum_line(buffer, zip_name));
+    manifest.push('\n');
+
+    write_atomically(&path, manifest)
+        .with_context(|| format!("failed to write cache manifest at {path:?}"))
+}
+
+fn extract_zip_archive(
+    entry_name: &str,
+    program_path: &Path,
+    mode: u32,
+    os: &str,
+    archive: &mut ZipArchive<Cursor<Vec<u8>>>,
+) -> Result<()> {
+    let mut file = archive
+        .by_name(entry_name)
+        .with_context(|| format!("archive has no entry named {entry_name}"))?;
+    let file_name = file.name().to_owned();
+    status!("Extracting {file_name} to {program_path:?}");
+
+    // Check the header before touching the existing binary, so that a bad archive leaves it intact
+    let mut header = [0; 4];
+    file.read_exact(&mut header)
+        .with_context(|| format!("failed to read {file_name} from archive"))?;
+    check_executable_format(&header, os)?;
+    if let Some(install_dir) = program_path.parent() {
+        check_disk_space(install_dir, file.size())?;
+    }
+
+    // Create a new file for the extracted file and set its permissions
+    let mut outfile = create_output_file(program_path, mode)?;
+
+    // Write the contents of the file to the output file
+    outfile
+        .write_all(&header)
+        .and_then(|_| io::copy(&mut file, &mut outfile))
+        .with_context(|| "failed to extract zip archive")?;
+
+    status!("Extracted archive to {program_path:?}");
+    Ok(())
+}
+
+/// Extracts the executables for `os` other than `skip` into `install_dir`, keeping their names.
+/// Entries that are not executables, like licenses, are left in the archive.
+fn extract_other_executables(
+    archive: &mut ZipArchive<Cursor<Vec<u8>>>,
+    skip: &str,
+    install_dir: &Path,
+    mode: u32,
+    os: &str,
+) -> Result<Vec<PathBuf>> {
+    let mut extracted = Vec::new();
+    for index in 0..archive.len() {
+        let mut file = archive
+            .by_index(index)
+            .with_context(|| "could not get item in archive")?;
+        let Some(file_name) = file.enclosed_name().and_then(|p| p.file_name()) else {
+            continue;
+        };
+        let file_name = file_name.to_owned();
+        if file.is_dir() || file_name == skip {
+            continue;
+        }
+
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)
+            .with_context(|| format!("failed to read {file_name:?} from archive"))?;
+        if check_executable_format(&contents, os).is_err() {
+            continue;
+        }
+
+        let path = install_dir.join(&file_name);
+        status!("Extracting {file_name:?} to {path:?}");
+        create_output_file(&path, mode)?
+            .write_all(&contents)
+            .with_context(|| format!("failed to extract {file_name:?}"))?;
+        extracted.push(path);
+    }
+
+    Ok(extracted)
+}
+
+fn detect_executable_format(header: &[u8]) -> Option<&'static str> {
+    match header {
+        [0x7f, b'E', b'L', b'F', ..] => Some("ELF"),
+        [0xfe, 0xed, 0xfa, 0xce | 0xcf, ..]
+        | [0xce | 0xcf, 0xfa, 0xed, 0xfe, ..]
+        | [0xca, 0xfe, 0xba, 0xbe, ..] => Some("Mach-O"),
+        [b'M', b'Z', ..] => Some("PE"),
+        _ => None,
+    }
+}
+
+/// Catches archives holding something other than a binary for the target, like an error page
+/// served by a misconfigured mirror.
+fn check_executable_format(header: &[u8], os: &str) -> Result<()> {
+    let expected = match os {
+        "darwin" => "Mach-O",
+        "windows" => "PE",
+        _ => "ELF",
+    };
+
+    match detect_executable_format(header) {
+        Some(actual) if actual == expected => Ok(()),
+        Some(actual) => {
+            bail!("extracted file is a {actual} executable, expected {expected} for {os}")
+        }
+        None => bail!("extracted file is not an executable, expected {expected} for {os}"),
+    }
+}
+
+fn parse_days(duration: &str) -> Result<u64, String> {
+    let invalid = || format!("{duration} is not a valid duration, expected e.g. 90d or 12w");
+    let (count, days_per_unit) = match (duration.strip_suffix('d'), duration.strip_suffix('w')) {
+        (Some(count), _) => (count, 1),
+        (_, Some(count)) => (count, 7),
+        _ => return Err(invalid()),
+    };
+    count
+        .parse::<u64>()
+        .ok()
+        .and_then(|count| count.checked_mul(days_per_unit))
+        .ok_or_else(invalid)
+}
+
+fn parse_mode(mode: &str) -> Result<u32, String> {
+    let digits = mode.strip_prefix("0o").unwrap_or(mode);
+    u32::from_str_radix(digits, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+        .ok_or_else(|| format!("{mode} is not a valid octal file mode"))
+}
+
+fn parse_retry_status(code: &str) -> Result<u16, String> {
+    let code = code
+        .parse()
+        .map_err(|_| format!("{code} is not an HTTP status code"))?;
+    check_retry_status(code).map(|_| code)
+}
+
+/// Retrying a successful response would only repeat it.
+fn check_retry_status(code: u16) -> Result<(), String> {
+    match code {
+        200..=299 => Err(format!("{code} is a success status and can't be retried")),
+        100..=599 => std::result::Result::Ok(()),
+        _ => Err(format!(
+            "{code} is not an HTTP status code, expected 100 to 599"
+        )),
+    }
+}
+
+fn write_program_to_stdout(
+    entry_name: &str,
+    os: &str,
+    mut archive: ZipArchive<Cursor<Vec<u8>>>,
+) -> Result<()> {
+    let contents = read_archive_entry(&mut archive, entry_name)?;
+    check_executable_format(&contents, os)?;
+
+    let mut stdout = io::stdout().lock();
+    stdout
+        .write_all(&contents)
+        .and_then(|_| stdout.flush())
+        .with_context(|| "failed to write binary to stdout")
+}
+
+fn read_archive_entry(
+    archive: &mut ZipArchive<Cursor<Vec<u8>>>,
+    entry_name: &str,
+) -> Result<Vec<u8>> {
+    let mut file = archive
+        .by_name(entry_name)
+        .with_context(|| format!("could not get {entry_name} in archive"))?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)
+        .with_context(|| format!("failed to read {entry_name} from archive"))?;
+
+    Ok(buffer)
+}
+
+#[cfg(unix)]
+fn create_output_file(program_path: &Path, mode: u32) -> Result<File> {
+    let file = File::create(program_path).map_err(|source| TfswitcherError::Io {
+        message: describe_create_error(program_path, &source),
+        source,
+    })?;
+    let mut perms = file
+        .metadata()
+        .with_context(|| "could not get file metadata")?
+        .permissions();
+    perms.set_mode(mode);
+    file.set_permissions(perms)
+        .with_context(|| "could not set file permissions")?;
+
+    Ok(file)
+}
+
+#[cfg(windows)]
+fn create_output_file(program_path: &Path, _mode: u32) -> Result<File> {
+    Ok(
+        File::create(program_path).map_err(|source| TfswitcherError::Io {
+            message: describe_create_error(program_path, &source),
+            source,
+        })?,
+    )
+}
+
+/// System directories like /usr/local/bin are usually only writable by root, so permission
+/// failures get a hint instead of the bare OS error.
+fn describe_create_error(path: &Path, error: &io::Error) -> String {
+    match error.kind() {
+        io::ErrorKind::PermissionDenied => format!(
+            "no permission to write {path:?}, re-run with elevated privileges (e.g. sudo) or pass --bin to install to a writable location"
+        ),
+        _ => format!("failed to create file at {path:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use html_to_string_macro::html;
+    use once_cell::sync::Lazy;
+
+    fn zip_archive(entries: &[(&str, &[u8])]) -> Result<ZipArchive<Cursor<Vec<u8>>>> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in entries {
+            writer.start_file(*name, Default::default())?;
+            io::Write::write_all(&mut writer, contents)?;
+        }
+        let buffer = writer.finish()?.into_inner();
+
+        Ok(ZipArchive::new(Cursor::new(buffer))?)
+    }
+
+    fn releases(versions: &[&str]) -> Result<Vec<ReleaseInfo>> {
+        versions
+            .iter()
+            .map(|v| ReleaseInfo::new(ProgramName::Terraform, v.to_string()))
+            .collect()
+    }
+
+    static LINES: Lazy<String> = Lazy::new(|| {
+        html!(
+        <html>
+            <head>
+                <title>"Terraform Versions | HashiCorp Releases"</title>
+            </head>
 
             <body>
                 <ul>
@@ -557,229 +5148,2505 @@ mod tests {
             </body>
         </html>
         )
-    });
+    });
+
+    #[test]
+    fn test_is_newer_version() -> Result<()> {
+        assert!(is_newer_version("0.4.0", "v0.5.0")?);
+        assert!(is_newer_version("0.4.0", "0.4.1")?);
+        assert!(!is_newer_version("0.4.0", "v0.4.0")?);
+        assert!(!is_newer_version("0.4.0", "v0.3.9")?);
+        assert!(is_newer_version("0.4.0", "nightly").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_version_output_terraform() {
+        let output = "Terraform v1.5.7\non linux_amd64\n";
+        assert_eq!(Some("1.5.7".to_owned()), parse_version_output(output));
+    }
+
+    #[test]
+    fn test_parse_version_output_opentofu() {
+        let output = "OpenTofu v1.6.0-beta1\non linux_amd64\n";
+        assert_eq!(Some("1.6.0-beta1".to_owned()), parse_version_output(output));
+    }
+
+    #[test]
+    fn test_parse_version_output_unknown() {
+        assert!(parse_version_output("tfswitcher 0.4.0").is_none());
+    }
+
+    #[test]
+    fn test_is_retryable_status() {
+        use reqwest::StatusCode;
+
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS, &[]));
+        assert!(!is_retryable_status(StatusCode::BAD_GATEWAY, &[]));
+        assert!(is_retryable_status(StatusCode::BAD_GATEWAY, &[502, 503]));
+        assert!(!is_retryable_status(StatusCode::NOT_FOUND, &[502, 503]));
+    }
+
+    #[test]
+    fn test_get_retry_delay() {
+        assert_eq!(Duration::from_secs(5), get_retry_delay(Some("5"), 1));
+        assert_eq!(Duration::from_secs(60), get_retry_delay(Some("3600"), 1));
+        assert_eq!(
+            Duration::from_secs(4),
+            get_retry_delay(Some("Wed, 21 Oct 2015 07:28:00 GMT"), 2)
+        );
+        assert_eq!(Duration::from_secs(2), get_retry_delay(None, 1));
+    }
+
+    #[test]
+    fn test_get_throttle_deadline() {
+        let now = Instant::now();
+        assert_eq!(
+            now + Duration::from_secs(2),
+            get_throttle_deadline(None, now, 2000, 1000)
+        );
+        assert_eq!(
+            now + Duration::from_secs(2),
+            get_throttle_deadline(Some(now - Duration::from_secs(5)), now, 2000, 1000)
+        );
+        // A second download waits behind the bytes the first one already reserved
+        assert_eq!(
+            now + Duration::from_secs(3),
+            get_throttle_deadline(Some(now + Duration::from_secs(1)), now, 2000, 1000)
+        );
+    }
+
+    #[test]
+    fn test_extract_zip_archive_entry_name() -> Result<()> {
+        let mut archive = zip_archive(&[
+            ("LICENSE.txt", b"license"),
+            ("bin/terraform-custom", b"\x7fELF custom"),
+        ])?;
+        let tmp_dir = TempDir::new("test_extract_zip_archive_entry_name")?;
+        let program_path = tmp_dir.path().join("terraform");
+
+        assert!(
+            extract_zip_archive("terraform", &program_path, 0o755, "linux", &mut archive).is_err()
+        );
+        extract_zip_archive(
+            "bin/terraform-custom",
+            &program_path,
+            0o755,
+            "linux",
+            &mut archive,
+        )?;
+        assert_eq!(b"\x7fELF custom".to_vec(), fs::read(&program_path)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_other_executables() -> Result<()> {
+        let mut archive = zip_archive(&[
+            ("terraform", b"\x7fELF main"),
+            ("terraform-helper", b"\x7fELF helper"),
+            ("LICENSE.txt", b"license"),
+        ])?;
+        let tmp_dir = TempDir::new("test_extract_other_executables")?;
+
+        let extracted =
+            extract_other_executables(&mut archive, "terraform", tmp_dir.path(), 0o755, "linux")?;
+        assert_eq!(vec![tmp_dir.path().join("terraform-helper")], extracted);
+        assert!(!tmp_dir.path().join("terraform").exists());
+        assert!(!tmp_dir.path().join("LICENSE.txt").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_executable_format() {
+        assert!(check_executable_format(b"\x7fELF", "linux").is_ok());
+        assert!(check_executable_format(&[0xcf, 0xfa, 0xed, 0xfe], "darwin").is_ok());
+        assert!(check_executable_format(b"MZ\x90\x00", "windows").is_ok());
+        assert!(check_executable_format(b"\x7fELF", "darwin").is_err());
+        assert!(check_executable_format(b"<htm", "linux").is_err());
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(Some(0o750), parse_mode("750").ok());
+        assert_eq!(Some(0o775), parse_mode("0775").ok());
+        assert_eq!(Some(0o755), parse_mode("0o755").ok());
+        assert!(parse_mode("789").is_err());
+        assert!(parse_mode("17777").is_err());
+        assert!(parse_mode("rwxr-xr-x").is_err());
+    }
+
+    #[test]
+    fn test_parse_retry_status() {
+        assert_eq!(Some(503), parse_retry_status("503").ok());
+        assert!(parse_retry_status("200").is_err());
+        assert!(parse_retry_status("600").is_err());
+        assert!(parse_retry_status("teapot").is_err());
+        assert!(check_retry_status(99).is_err());
+        assert!(check_retry_status(429).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_output_file_mode() -> Result<()> {
+        let tmp_dir = TempDir::new("test_create_output_file_mode")?;
+        let file_path = tmp_dir.path().join("terraform");
+
+        let file = create_output_file(&file_path, 0o750)?;
+        assert_eq!(0o750, file.metadata()?.permissions().mode() & 0o7777);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_archive_entry() -> Result<()> {
+        let mut archive = zip_archive(&[("LICENSE.txt", b"license"), ("terraform", b"binary")])?;
+        assert_eq!(
+            b"binary".to_vec(),
+            read_archive_entry(&mut archive, "terraform")?
+        );
+        assert!(read_archive_entry(&mut archive, "tofu").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_config_arguments_bool_flags_disabled_from_cli() -> Result<()> {
+        let config_file = r#"list_all = true
+opentofu = true"#;
+
+        let tmp_dir = TempDir::new("test_parse_config_arguments_bool_flags_disabled_from_cli")?;
+        let tmp_dir_path = tmp_dir.path();
+        let file_path = tmp_dir_path.join(CONFIG_FILE_NAME);
+        fs::write(file_path, config_file)?;
+
+        let mut args = Args::default();
+        parse_config_arguments(tmp_dir_path.to_path_buf(), &mut args)?;
+        assert!(args.list_all);
+        assert!(args.opentofu);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_config_arguments_invalid_retry_on() -> Result<()> {
+        let tmp_dir = TempDir::new("test_parse_config_arguments_invalid_retry_on")?;
+        let tmp_dir_path = tmp_dir.path();
+        fs::write(tmp_dir_path.join(CONFIG_FILE_NAME), "retry_on = [503, 200]")?;
+
+        let mut args = Args::default();
+        let error = parse_config_arguments(tmp_dir_path.to_path_buf(), &mut args).unwrap_err();
+        assert!(error.to_string().contains("200 is a success status"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_config_arguments_invalid_jobs() -> Result<()> {
+        let tmp_dir = TempDir::new("test_parse_config_arguments_invalid_jobs")?;
+        let tmp_dir_path = tmp_dir.path();
+        fs::write(tmp_dir_path.join(CONFIG_FILE_NAME), "jobs = 0")?;
+
+        let mut args = Args::default();
+        assert!(parse_config_arguments(tmp_dir_path.to_path_buf(), &mut args).is_err());
+        assert_eq!(
+            1,
+            get_jobs(&Args {
+                jobs: Some(0),
+                ..Default::default()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_config_arguments_bool_flags_enabled_from_cli() -> Result<()> {
+        let tmp_dir = TempDir::new("test_parse_config_arguments_bool_flags_enabled_from_cli")?;
+        let tmp_dir_path = tmp_dir.path();
+        let file_path = tmp_dir_path.join(CONFIG_FILE_NAME);
+        File::create(file_path)?;
+
+        let mut args = Args {
+            list_all: true,
+            opentofu: true,
+            ..Default::default()
+        };
+        parse_config_arguments(tmp_dir_path.to_path_buf(), &mut args)?;
+        assert!(args.list_all);
+        assert!(args.opentofu);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_version_from_env() {
+        assert_eq!(
+            Some("1.5.7".into()),
+            get_version_from_env(Some("1.5.7".into()))
+        );
+        assert_eq!(None, get_version_from_env(Some(String::new())));
+        assert_eq!(None, get_version_from_env(None));
+    }
+
+    #[test]
+    fn test_format_error_json() {
+        let error = anyhow::Error::from(TfswitcherError::VersionNotFound("no version".into()))
+            .context("failed to install");
+        assert_eq!(
+            r#"{"error":"failed to install: no version","kind":"version_not_found"}"#,
+            format_error_json(&error)
+        );
+        assert_eq!("other", get_error_kind(&anyhow::anyhow!("unexpected")));
+    }
+
+    #[test]
+    fn test_parse_exec_args() -> Result<()> {
+        let args = Args::try_parse_from([
+            "tfswitcher",
+            "--exec",
+            "1.5.7",
+            "--",
+            "plan",
+            "-out",
+            "tf.plan",
+        ])?;
+        assert!(args.exec);
+        assert_eq!(Some("1.5.7".into()), args.install_version);
+        assert_eq!(vec!["plan", "-out", "tf.plan"], args.exec_args);
+
+        assert!(Args::try_parse_from(["tfswitcher", "1.5.7", "--", "plan"]).is_err());
+        assert!(Args::try_parse_from(["tfswitcher", "--exec", "--list"]).is_err());
+        assert!(Args::try_parse_from(["tfswitcher", "--exec", "--audit"]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_install_summary() {
+        let path = Path::new("/home/me/.local/bin/terraform");
+        assert_eq!(
+            "Installed terraform 1.5.7 -> /home/me/.local/bin/terraform",
+            format_install_summary(
+                OutputFormat::Text,
+                &ProgramName::Terraform,
+                "1.5.7",
+                path,
+                None
+            )
+        );
+        assert_eq!(
+            r#"{"path":"/home/me/.local/bin/terraform","program":"terraform","version":"1.5.7"}"#,
+            format_install_summary(
+                OutputFormat::Json,
+                &ProgramName::Terraform,
+                "1.5.7",
+                path,
+                None
+            )
+        );
+
+        let url = ProgramName::OpenTofu.get_changelog_url("1.6.0");
+        assert_eq!(
+            "https://github.com/opentofu/opentofu/releases/tag/v1.6.0",
+            url
+        );
+        assert_eq!(
+            r#"{"changelog_url":"https://github.com/opentofu/opentofu/releases/tag/v1.6.0","path":"/home/me/.local/bin/terraform","program":"tofu","version":"1.6.0"}"#,
+            format_install_summary(
+                OutputFormat::Json,
+                &ProgramName::OpenTofu,
+                "1.6.0",
+                path,
+                Some(&url)
+            )
+        );
+    }
+
+    #[test]
+    fn test_format_config() -> Result<()> {
+        let args = Args {
+            binary_location: Some("/usr/local/bin/terraform".into()),
+            install_version: Some("1.5.7".into()),
+            list: true,
+            ..Default::default()
+        };
+        let config = format_config(&args)?;
+        assert!(config.contains("bin = \"/usr/local/bin/terraform\""));
+        assert!(config.contains("version = \"1.5.7\""));
+        assert!(!config.contains("list ="));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_unencrypted() {
+        assert!(is_unencrypted("http://mirror.lab/terraform"));
+        assert!(is_unencrypted("HTTP://mirror.lab/terraform"));
+        assert!(!is_unencrypted("https://mirror.example.com/terraform"));
+        assert!(!is_unencrypted("http"));
+    }
+
+    #[test]
+    fn test_load_ca_certificate() -> Result<()> {
+        let tmp_dir = TempDir::new("test_load_ca_certificate")?;
+        let path = tmp_dir.path().join("ca.pem");
+
+        assert!(load_ca_certificate(&path).is_err());
+        fs::write(&path, "not a certificate")?;
+        let err = load_ca_certificate(&path).unwrap_err();
+        assert!(err.to_string().contains("failed to parse CA certificate"));
+
+        let args: Args = toml::from_str(r#"ca_cert = ["/etc/ssl/corp.pem"]"#)?;
+        assert_eq!(vec![PathBuf::from("/etc/ssl/corp.pem")], args.ca_cert);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_insecure_skip_tls_verify_is_not_configurable() {
+        let args: Result<Args, _> = toml::from_str("insecure_skip_tls_verify = true");
+        assert!(args.map_or(true, |args| !args.insecure_skip_tls_verify));
+    }
+
+    #[test]
+    fn test_parse_min_tls() -> Result<()> {
+        let args: Args = toml::from_str(r#"min_tls = "1.3""#)?;
+        assert_eq!(Some(TlsVersion::Tls13), args.min_tls);
+        assert!(format_config(&args)?.contains(r#"min_tls = "1.3""#));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_file_in_cwd() -> Result<()> {
+        let expected_config_file = Args {
+            binary_location: Some("test_load_config_file_in_cwd".into()),
+            list_all: true,
+            opentofu: true,
+            install_version: Some("test_load_config_file_in_cwd".to_owned()),
+            count: Some(10),
+            ..Default::default()
+        };
+        let config_file = r#"bin = "test_load_config_file_in_cwd"
+count = 10
+list_all = true
+opentofu = true
+version = "test_load_config_file_in_cwd""#;
+
+        let tmp_dir = TempDir::new("test_load_config_file_in_cwd")?;
+        let tmp_dir_path = tmp_dir.path();
+        let file_path = tmp_dir_path.join(CONFIG_FILE_NAME);
+        fs::write(file_path, config_file)?;
+
+        let actual_config_file = load_config_file(tmp_dir_path.to_path_buf(), None, None)?;
+        assert_eq!(Some(expected_config_file), actual_config_file);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_file_in_home() -> Result<()> {
+        let expected_config_file = Args {
+            binary_location: Some("test_load_config_file_in_home".into()),
+            list_all: true,
+            opentofu: true,
+            install_version: Some("test_load_config_file_in_home".to_owned()),
+            ..Default::default()
+        };
+        let config_file = r#"bin = "test_load_config_file_in_home"
+list_all = true
+opentofu = true
+version = "test_load_config_file_in_home""#;
+
+        let tmp_dir = TempDir::new("test_load_config_file_in_home")?;
+        let tmp_dir_path = tmp_dir.path();
+        let file_path = tmp_dir_path.join(CONFIG_FILE_NAME);
+        fs::write(file_path, config_file)?;
+
+        let actual_config_file =
+            load_config_file(".".into(), None, Some(tmp_dir_path.to_path_buf()))?;
+        assert_eq!(Some(expected_config_file), actual_config_file);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_file_in_config_dir() -> Result<()> {
+        let expected_config_file = Args {
+            binary_location: Some("test_load_config_file_in_config_dir".into()),
+            ..Default::default()
+        };
+        let config_file = r#"bin = "test_load_config_file_in_config_dir""#;
+
+        let tmp_dir = TempDir::new("test_load_config_file_in_config_dir")?;
+        let tmp_dir_path = tmp_dir.path();
+        let file_path = tmp_dir_path.join(XDG_CONFIG_FILE_NAME);
+        fs::write(file_path, config_file)?;
+
+        let actual_config_file =
+            load_config_file(".".into(), Some(tmp_dir_path.to_path_buf()), None)?;
+        assert_eq!(Some(expected_config_file), actual_config_file);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_file_not_present() -> Result<()> {
+        let tmp_dir = TempDir::new("test_load_config_file_not_present")?;
+        let tmp_dir_path = tmp_dir.path();
+
+        let actual_config_file = load_config_file(
+            ".".into(),
+            Some(tmp_dir_path.to_path_buf()),
+            Some(tmp_dir_path.to_path_buf()),
+        )?;
+        assert!(actual_config_file.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_http_client_ipv4_and_ipv6() {
+        let args = Args {
+            ipv4: true,
+            ipv6: true,
+            ..Default::default()
+        };
+        assert!(build_http_client(&args).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_optional_checksum_not_found() -> Result<()> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            while let std::result::Result::Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 1024];
+                let _ = stream.read(&mut request).await;
+                let _ = stream
+                    .write_all(
+                        b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                    )
+                    .await;
+            }
+        });
+
+        let client = Client::new();
+        let target = Target {
+            mirror_url: Some(format!("http://{addr}")),
+            ..Default::default()
+        };
+        let release = ReleaseInfo::new(ProgramName::Terraform, "0.1.0".into())?;
+
+        let args = Args {
+            verify: true,
+            ..Default::default()
+        };
+        assert!(get_optional_checksum(&client, &args, &target, &release)
+            .await?
+            .is_none());
+
+        let args = Args {
+            verify: true,
+            require_checksum: true,
+            ..Default::default()
+        };
+        assert!(get_optional_checksum(&client, &args, &target, &release)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_proxy_bridge_rejects_other_connections() -> Result<()> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let tmp_dir = TempDir::new("test_unix_proxy_bridge_rejects_other_connections")?;
+        let socket_path = tmp_dir.path().join("proxy.sock");
+        let _proxy = tokio::net::UnixListener::bind(&socket_path)?;
+        let (addr, _) = start_unix_proxy_bridge(&socket_path)?;
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET http://releases.invalid/ HTTP/1.1\r\nHost: releases.invalid\r\n\r\n")
+            .await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+
+        assert!(response.starts_with("HTTP/1.1 407 "));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_strip_proxy_credentials() {
+        let credentials = get_proxy_credentials("0123");
+        let head = format!(
+            "CONNECT releases.hashicorp.com:443 HTTP/1.1\r\nHost: releases.hashicorp.com:443\r\nProxy-Authorization: {credentials}\r\n\r\n"
+        );
+        assert_eq!(
+            Some(
+                b"CONNECT releases.hashicorp.com:443 HTTP/1.1\r\nHost: releases.hashicorp.com:443\r\n\r\n"
+                    .to_vec()
+            ),
+            strip_proxy_credentials(head.as_bytes(), &credentials)
+        );
+        assert_eq!(
+            None,
+            strip_proxy_credentials(head.as_bytes(), &get_proxy_credentials("4567"))
+        );
+        assert_eq!(
+            None,
+            strip_proxy_credentials(
+                b"GET http://releases.invalid/ HTTP/1.1\r\nHost: releases.invalid\r\n\r\n",
+                &credentials
+            )
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_build_http_client_unix_proxy() -> Result<()> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let tmp_dir = TempDir::new("test_build_http_client_unix_proxy")?;
+        let socket_path = tmp_dir.path().join("proxy.sock");
+        let proxy = tokio::net::UnixListener::bind(&socket_path)?;
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = proxy.accept().await?;
+            let mut request = vec![0; 1024];
+            let len = stream.read(&mut request).await?;
+            stream
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\nconnection: close\r\n\r\nproxy",
+                )
+                .await?;
+            Ok(String::from_utf8_lossy(&request[..len]).into_owned())
+        });
+
+        let args = Args {
+            unix_proxy: Some(socket_path),
+            ..Default::default()
+        };
+        let client = build_http_client(&args)?;
+        let body = get_http(&client, "http://releases.invalid/terraform", &[])
+            .await?
+            .text()
+            .await?;
+
+        assert_eq!("proxy", body);
+        let request = server.await??;
+        assert!(request.starts_with("GET http://releases.invalid/terraform HTTP/1.1"));
+        assert!(!request.to_lowercase().contains("proxy-authorization"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_capture_terraform_versions() -> Result<()> {
+        let expected_versions: Vec<ReleaseInfo> =
+            vec!["1.3.0", "1.2.0", "1.1.0", "1.0.0", "0.15.0"]
+                .into_iter()
+                .map(|v| ReleaseInfo::new(ProgramName::Terraform, v.into()))
+                .collect::<Result<_>>()?;
+        let actual_versions = capture_terraform_versions(&Args::default(), &LINES);
+
+        assert_eq!(expected_versions, actual_versions);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_capture_terraform_versions_list_all() -> Result<()> {
+        let expected_versions: Vec<ReleaseInfo> = vec![
+            "1.3.0",
+            "1.3.0-rc1",
+            "1.3.0-beta1",
+            "1.3.0-alpha20220608",
+            "1.2.0",
+            "1.2.0-rc1",
+            "1.2.0-beta1",
+            "1.2.0-alpha20220413",
+            "1.2.0-alpha-20220328",
+            "1.1.0",
+            "1.1.0-rc1",
+            "1.1.0-beta1",
+            "1.1.0-alpha20211029",
+            "1.0.0",
+            "0.15.0",
+            "0.15.0-rc1",
+            "0.15.0-beta1",
+            "0.15.0-alpha20210107",
+        ]
+        .into_iter()
+        .map(|v| ReleaseInfo::new(ProgramName::Terraform, v.into()))
+        .collect::<Result<_>>()?;
+        let args = Args {
+            list_all: true,
+            ..Default::default()
+        };
+        let actual_versions = capture_terraform_versions(&args, &LINES);
+
+        assert_eq!(expected_versions, actual_versions);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_versions() -> Result<()> {
+        let mut versions = releases(&["1.2.0", "1.10.0", "1.10.0-rc1", "1.9.1"])?;
+        sort_versions(&mut versions);
+
+        assert_eq!(
+            vec!["1.10.0", "1.10.0-rc1", "1.9.1", "1.2.0"],
+            versions.get_versions()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_versions_since() -> Result<()> {
+        let versions = releases(&["1.3.0", "1.2.0", "1.1.0"])?;
+
+        let actual_versions = filter_versions_since(versions, "1.2.0")?;
+        assert_eq!(vec!["1.3.0"], actual_versions.get_versions());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_version_lists() {
+        let previous = vec!["1.5.7".to_owned(), "1.5.6".into(), "1.5.0-beta1".into()];
+        let current = vec!["1.6.0".to_owned(), "1.5.7".into(), "1.5.6".into()];
+
+        assert_eq!(
+            vec!["+ 1.6.0", "- 1.5.0-beta1"],
+            diff_version_lists(&previous, &current)
+        );
+        assert!(diff_version_lists(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_filter_versions_since_invalid() {
+        assert!(filter_versions_since(vec![], "latest").is_err());
+    }
+
+    #[test]
+    fn test_filter_versions_by_constraint() -> Result<()> {
+        let versions = releases(&["1.6.0", "1.5.7", "1.3.0", "1.2.0"])?;
+
+        let actual_versions = filter_versions_by_constraint(versions, ">= 1.3, < 1.6")?;
+        assert_eq!(vec!["1.5.7", "1.3.0"], actual_versions.get_versions());
+        assert!(filter_versions_by_constraint(vec![], "not a constraint").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_versions_json() -> Result<()> {
+        let mut versions = releases(&["1.6.0-rc1", "1.5.7"])?;
+        versions[1].release_date = Some("2023-08-31T14:36:43.000Z".into());
+
+        assert_eq!(
+            r#"[{"version":"1.6.0-rc1","prerelease":true},{"version":"1.5.7","prerelease":false,"release_date":"2023-08-31T14:36:43.000Z"}]"#,
+            format_versions_json(&versions)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_versions_table() -> Result<()> {
+        let versions = vec![
+            ReleaseInfo::new(ProgramName::Terraform, "1.3.0".into())?,
+            ReleaseInfo::new(ProgramName::Terraform, "1.3.0-rc1".into())?,
+        ];
+
+        let tmp_dir = TempDir::new("test_format_versions_table")?;
+        let target = Target::default();
+        File::create(tmp_dir.path().join(versions[0].get_zip_name(&target)))?;
+
+        let expected_table = "VERSION    CHANNEL     CACHED
+1.3.0      stable      yes
+1.3.0-rc1  prerelease  no
+";
+        let actual_table = format_versions_table(&versions, &target, Some(tmp_dir.path()));
+        assert_eq!(expected_table, actual_table);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hashicorp_release_is_withdrawn() -> Result<()> {
+        let release: HashiCorpRelease = serde_json::from_str(
+            r#"{"version": "1.5.7", "timestamp_created": "2023-08-31T14:36:43.000Z", "status": {"state": "withdrawn"}}"#,
+        )?;
+        assert!(release.is_withdrawn());
+
+        let release: HashiCorpRelease = serde_json::from_str(
+            r#"{"version": "1.5.7", "timestamp_created": "2023-08-31T14:36:43.000Z", "status": {"state": "supported"}}"#,
+        )?;
+        assert!(!release.is_withdrawn());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_version_with_date() -> Result<()> {
+        let mut release = ReleaseInfo::new(ProgramName::Terraform, "1.5.7".into())?;
+        assert_eq!("1.5.7", format_version_with_date(&release));
+
+        release.release_date = Some("2023-08-31T14:36:43.000Z".into());
+        assert_eq!("1.5.7 (2023-08-31)", format_version_with_date(&release));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_prompt_item() -> Result<()> {
+        let stable = ReleaseInfo::new(ProgramName::Terraform, "1.5.7".into())?;
+        let prerelease = ReleaseInfo::new(ProgramName::Terraform, "1.6.0-beta1".into())?;
+
+        assert_eq!("1.5.7", format_prompt_item(&stable, false, false));
+        assert_eq!("1.5.7", format_prompt_item(&stable, false, true));
+        assert_eq!(
+            "1.6.0-beta1 (pre)",
+            format_prompt_item(&prerelease, false, false)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_info_strips_v_prefix() -> Result<()> {
+        let release = ReleaseInfo::new(ProgramName::Terraform, "v1.5.7".into())?;
+        assert_eq!("1.5.7", release.raw_version);
+        assert_eq!("vagrant", strip_version_prefix("vagrant"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_info_invalid_version() {
+        assert!(ReleaseInfo::new(ProgramName::Terraform, "1.5".into()).is_err());
+    }
+
+    #[test]
+    fn test_release_info_keeps_raw_version() -> Result<()> {
+        let release = ReleaseInfo::new(ProgramName::Terraform, "1.2.0-alpha-20220328".into())?;
+        assert!(release.is_prerelease());
+        assert_eq!(
+            "terraform_1.2.0-alpha-20220328_SHA256SUMS",
+            release.get_checksums_name()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_zip_name() -> Result<()> {
+        let release = ReleaseInfo::new(ProgramName::OpenTofu, "1.6.0".into())?;
+        let target = Target {
+            os: "linux".into(),
+            arch: "arm64".into(),
+            ..Default::default()
+        };
+        assert_eq!("tofu_1.6.0_linux_arm64.zip", release.get_zip_name(&target));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_zip_name_from_template() -> Result<()> {
+        let release = ReleaseInfo::new(ProgramName::Terraform, "1.5.7".into())?;
+        let target = Target {
+            os: "linux".into(),
+            arch: "amd64".into(),
+            archive_name_template: "{product}-{version}-{os}-{arch}-ent.zip".into(),
+            mirror_url: None,
+            libc: None,
+        };
+        assert_eq!(
+            "terraform-1.5.7-linux-amd64-ent.zip",
+            release.get_zip_name(&target)
+        );
+
+        let target = Target {
+            archive_name_template: "{product}_{version}_{os}_{arch}_{libc}.zip".into(),
+            libc: Some(Libc::Musl),
+            ..target
+        };
+        assert_eq!(
+            "terraform_1.5.7_linux_amd64_musl.zip",
+            release.get_zip_name(&target)
+        );
+        let args = Args {
+            os: Some("darwin".into()),
+            archive_name_template: Some(target.archive_name_template.clone()),
+            ..Default::default()
+        };
+        assert_eq!(Some(Libc::Gnu), Target::from_args(&args).libc);
+        assert_eq!(None, Target::from_args(&Args::default()).libc);
+        assert!(is_musl_ldd_output("musl libc (x86_64)\nVersion 1.2.4\n"));
+        assert!(!is_musl_ldd_output("ldd (GNU libc) 2.36\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_terragrunt_constraint() {
+        let contents = r#"
+# terraform_version_constraint = ">= 0.12"
+terraform_version_constraint = ">= 1.4, < 1.6"
+
+terraform {
+  source = "../modules//vpc"
+}
+"#;
+        assert_eq!(
+            Some(">= 1.4, < 1.6".to_owned()),
+            parse_terragrunt_constraint(contents)
+        );
+    }
+
+    #[test]
+    fn test_get_constraint_from_terragrunt_not_present() -> Result<()> {
+        let tmp_dir = TempDir::new("test_get_constraint_from_terragrunt_not_present")?;
+        assert!(get_constraint_from_terragrunt(tmp_dir.path())?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_download_url() -> Result<()> {
+        let target = Target {
+            os: "linux".into(),
+            arch: "amd64".into(),
+            ..Default::default()
+        };
+
+        let release = ReleaseInfo::new(ProgramName::Terraform, "1.5.7".into())?;
+        assert_eq!(
+            "https://releases.hashicorp.com/terraform/1.5.7/terraform_1.5.7_linux_amd64.zip",
+            release.get_download_url(&target)
+        );
+
+        let release = ReleaseInfo::new(ProgramName::OpenTofu, "1.6.0".into())?;
+        assert_eq!(
+            "https://github.com/opentofu/opentofu/releases/download/v1.6.0/tofu_1.6.0_linux_amd64.zip",
+            release.get_download_url(&target)
+        );
+
+        let target = Target {
+            mirror_url: Some("https://mirror.example.com/terraform/".into()),
+            ..target
+        };
+        let release = ReleaseInfo::new(ProgramName::Terraform, "1.5.7".into())?;
+        assert_eq!(
+            "https://mirror.example.com/terraform/1.5.7/terraform_1.5.7_linux_amd64.zip",
+            release.get_download_url(&target)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_version_from_module() -> Result<()> {
+        let expected_release = ReleaseInfo::new(ProgramName::Terraform, "1.0.0".into())?;
+        let versions = vec![expected_release.clone()];
+
+        let tmp_dir = TempDir::new("test_get_version_from_module")?;
+        let tmp_dir_path = tmp_dir.path();
+        let file_path = tmp_dir_path.join("version.tf");
+        fs::write(file_path, r#"terraform { required_version = "~>1.0.0" }"#)?;
+
+        let actual_version = get_version_from_module(&Args::default(), tmp_dir_path, &versions)?;
+        assert_eq!(Some(expected_release), actual_version);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_tool_versions() {
+        let contents =
+            "nodejs 20.5.0\n# terraform 0.15.0\nterraform 1.5.7 1.5.6 # pinned\nopentofu 1.6.0\n";
+        assert_eq!(
+            Some("1.5.7".to_owned()),
+            parse_tool_versions(contents, "terraform")
+        );
+        assert_eq!(
+            Some("1.6.0".to_owned()),
+            parse_tool_versions(contents, "opentofu")
+        );
+        assert!(parse_tool_versions(contents, "python").is_none());
+    }
+
+    #[test]
+    fn test_get_version_from_tool_versions_in_parent() -> Result<()> {
+        let tmp_dir = TempDir::new("test_get_version_from_tool_versions_in_parent")?;
+        let sub_dir = tmp_dir.path().join("module");
+        fs::create_dir_all(&sub_dir)?;
+        fs::write(
+            tmp_dir.path().join(TOOL_VERSIONS_FILE_NAME),
+            "terraform 1.5.7\n",
+        )?;
+
+        let actual_version = get_version_from_tool_versions(&sub_dir, &ProgramName::Terraform)?;
+        assert_eq!(Some("1.5.7".to_owned()), actual_version);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_serialization() -> Result<()> {
+        let event = Event::Downloading {
+            bytes: 10,
+            total: Some(20),
+        };
+        assert_eq!(
+            r#"{"event":"downloading","bytes":10,"total":20}"#,
+            serde_json::to_string(&event)?
+        );
+        assert_eq!(
+            r#"{"event":"resolving"}"#,
+            serde_json::to_string(&Event::Resolving)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_version_annotation() {
+        let contents = "FROM alpine\n# terraform-version: 1.5.7\nRUN true\n";
+        assert_eq!(
+            Some("1.5.7".into()),
+            parse_version_annotation(contents, &ProgramName::Terraform)
+        );
+        assert_eq!(
+            Some("1.6.0".into()),
+            parse_version_annotation("// tofu-version: 1.6.0", &ProgramName::OpenTofu)
+        );
+        assert!(parse_version_annotation(contents, &ProgramName::OpenTofu).is_none());
+        assert!(
+            parse_version_annotation("terraform-version: 1.5.7", &ProgramName::Terraform).is_none()
+        );
+    }
+
+    #[test]
+    fn test_parse_hcl_constraint() -> Result<()> {
+        let contents = r#"
+packer {
+  required_version = ">= 1.9.0"
+}
+"#;
+        assert_eq!(Some(">= 1.9.0".into()), parse_hcl_constraint(contents)?);
+        assert_eq!(None, parse_hcl_constraint("locals {}")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json_constraint() -> Result<()> {
+        let contents = r#"{"terraform": [{"required_version": "~> 1.5.0"}]}"#;
+        assert_eq!(Some("~> 1.5.0".into()), parse_json_constraint(contents)?);
+
+        let contents = r#"{"terraform": {"required_version": ">= 1.0"}}"#;
+        assert_eq!(Some(">= 1.0".into()), parse_json_constraint(contents)?);
+        assert_eq!(None, parse_json_constraint("{}")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_min_version() -> Result<()> {
+        let release = ReleaseInfo::new(ProgramName::Terraform, "1.2.9".into())?;
+
+        assert!(check_min_version(None, &release).is_ok());
+        assert!(check_min_version(Some("1.2.9"), &release).is_ok());
+        assert!(check_min_version(Some("1.3.0"), &release).is_err());
+        assert!(check_min_version(Some("latest"), &release).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_newest_on_channel() -> Result<()> {
+        let versions = releases(&["1.7.0-alpha20231025", "1.6.0-rc1", "1.6.0-beta2", "1.5.7"])?;
+
+        assert_eq!(
+            Some(versions[3].clone()),
+            get_newest_on_channel(&versions, Channel::Stable)
+        );
+        assert_eq!(
+            Some(versions[1].clone()),
+            get_newest_on_channel(&versions, Channel::Rc)
+        );
+        assert_eq!(
+            Some(versions[0].clone()),
+            get_newest_on_channel(&versions, Channel::Alpha)
+        );
+        assert!(get_newest_on_channel(&versions[3..], Channel::Beta).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_policy() -> Result<()> {
+        let expected = Policy {
+            terraform: Some(PolicyEntry {
+                constraint: ">=1.5, <1.7".into(),
+            }),
+            opentofu: None,
+        };
+        assert_eq!(
+            expected,
+            parse_policy(r#"{"terraform": {"constraint": ">=1.5, <1.7"}}"#)?
+        );
+        assert_eq!(
+            expected,
+            parse_policy("[terraform]\nconstraint = \">=1.5, <1.7\"\n")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_policy() -> Result<()> {
+        let release = ReleaseInfo::new(ProgramName::Terraform, "1.7.0".into())?;
+
+        assert!(check_policy(None, &release).is_ok());
+        assert!(check_policy(Some(">=1.5, <1.8"), &release).is_ok());
+        assert!(check_policy(Some(">=1.5, <1.7"), &release).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_checksum_line() {
+        assert_eq!(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  terraform_1.5.7_linux_amd64.zip",
+            format_checksum_line(b"", "terraform_1.5.7_linux_amd64.zip")
+        );
+    }
+
+    #[test]
+    fn test_confirm() -> Result<()> {
+        let paths = vec![PathBuf::from("/cache/terraform_1.5.7_linux_amd64.zip")];
+
+        let args = Args {
+            no_prompt: true,
+            ..Default::default()
+        };
+        assert!(confirm(&args, "Continue?").is_err());
+        assert!(confirm_removal(&args, &paths).is_err());
+        confirm_removal(&args, &[])?;
+        let args = Args {
+            dry_run: true,
+            ..args
+        };
+        confirm_removal(&args, &paths)?;
+        let args = Args {
+            yes: true,
+            ..Default::default()
+        };
+        confirm(&args, "Continue?")?;
+        confirm_removal(&args, &paths)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_assume_default() {
+        assert!(is_assume_default(Some("1")));
+        assert!(is_assume_default(Some("true")));
+        assert!(!is_assume_default(Some("0")));
+        assert!(!is_assume_default(Some("FALSE")));
+        assert!(!is_assume_default(Some("")));
+        assert!(!is_assume_default(None));
+    }
+
+    #[test]
+    fn test_get_default_selection() -> Result<()> {
+        let versions = releases(&["1.6.0", "1.5.7", "1.5.6"])?;
+
+        assert_eq!(0, get_default_selection(&versions, None));
+        assert_eq!(1, get_default_selection(&versions, Some("1.5.7")));
+        assert_eq!(2, get_default_selection(&versions, Some("v1.5.6")));
+        assert_eq!(0, get_default_selection(&versions, Some("1.4.0")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_complete_html_index() {
+        let index = "<html><body><ul>\n<li><a href=\"/terraform/1.5.7/\">terraform_1.5.7</a></li>\n</ul></body></html>\n";
+        assert!(is_complete_html_index(index));
+        assert!(is_complete_html_index(&index.to_uppercase()));
+        assert!(!is_complete_html_index(&index[..index.len() / 2]));
+        assert!(!is_complete_html_index(""));
+    }
+
+    #[test]
+    fn test_parse_env_file_version() {
+        let env_file =
+            "# Pinned tools\nTFLINT_VERSION=0.48.0\nexport TERRAFORM_VERSION=\"1.5.7\"\n";
+        assert_eq!(
+            Some("1.5.7".into()),
+            parse_env_file_version(env_file, "TERRAFORM_VERSION")
+        );
+        assert_eq!(
+            Some("1.6.0".into()),
+            parse_env_file_version("TOFU_VERSION ?= 1.6.0 # pinned\n", "TOFU_VERSION")
+        );
+        assert_eq!(
+            Some("1.4.6".into()),
+            parse_env_file_version("TERRAFORM_VERSION := 1.4.6\n", "TERRAFORM_VERSION")
+        );
+        assert!(
+            parse_env_file_version("MY_TERRAFORM_VERSION=1.5.7\n", "TERRAFORM_VERSION").is_none()
+        );
+        assert!(parse_env_file_version(env_file, "TOFU_VERSION").is_none());
+    }
+
+    #[test]
+    fn test_get_version_by_index() -> Result<()> {
+        let versions = releases(&["1.2.1", "1.2.0", "1.1.0"])?;
+
+        assert_eq!(versions[0], get_version_by_index(&versions, 0)?);
+        assert_eq!(versions[1], get_version_by_index(&versions, 1)?);
+        assert!(get_version_by_index(&versions, 3).is_err());
+        assert!(get_version_by_index(&versions, -1).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_days() {
+        assert_eq!(Some(90), parse_days("90d").ok());
+        assert_eq!(Some(84), parse_days("12w").ok());
+        assert!(parse_days("90").is_err());
+        assert!(parse_days("d").is_err());
+        assert!(parse_days("3m").is_err());
+        assert!(parse_days("9é").is_err());
+    }
+
+    #[test]
+    fn test_parse_days_since_epoch() {
+        assert_eq!(Some(0), parse_days_since_epoch("1970-01-01"));
+        assert_eq!(
+            Some(19600),
+            parse_days_since_epoch("2023-08-31T14:36:43.000Z")
+        );
+        assert_eq!(Some(19782), parse_days_since_epoch("2024-02-29T00:00:00Z"));
+        assert!(parse_days_since_epoch("2023-13-01").is_none());
+        assert!(parse_days_since_epoch("yesterday").is_none());
+    }
+
+    #[test]
+    fn test_filter_versions_newer_than() -> Result<()> {
+        let mut versions = releases(&["1.6.0", "1.5.7", "1.5.0"])?;
+        let today = parse_days_since_epoch("2023-10-04").unwrap_or_default();
+        assert!(filter_versions_newer_than(versions.clone(), 90, today).is_err());
+
+        versions[0].release_date = Some("2023-10-04T10:00:00Z".into());
+        versions[1].release_date = Some("2023-08-31T14:36:43.000Z".into());
+        versions[2].release_date = Some("2023-06-12T12:00:00Z".into());
+        assert_eq!(
+            vec!["1.6.0", "1.5.7"],
+            filter_versions_newer_than(versions.clone(), 90, today)?.get_versions()
+        );
+        assert_eq!(
+            vec!["1.6.0"],
+            filter_versions_newer_than(versions, 7, today)?.get_versions()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_installed_version() -> Result<()> {
+        let release = |v: &str| ReleaseInfo::new(ProgramName::Terraform, v.into());
+
+        check_installed_version(&release("1.5.7")?, &release("v1.5.7")?)?;
+        let err = check_installed_version(&release("1.5.6")?, &release("1.5.7")?).unwrap_err();
+        assert_eq!(
+            "installed Terraform version is 1.5.6, expected 1.5.7",
+            err.to_string()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_previous_version() -> Result<()> {
+        let versions = releases(&["1.3.0-rc1", "1.2.1", "1.2.0", "1.1.0"])?;
+        let installed = |v: &str| ReleaseInfo::new(ProgramName::Terraform, v.into());
+
+        assert_eq!(
+            versions[2],
+            get_previous_version(&versions, &installed("1.2.1")?)?
+        );
+        assert_eq!(
+            versions[1],
+            get_previous_version(&versions, &installed("1.3.0-rc1")?)?
+        );
+        assert_eq!(
+            versions[1],
+            get_previous_version(&versions, &installed("1.2.5")?)?
+        );
+        assert!(get_previous_version(&versions, &installed("1.1.0")?).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_latest_version() -> Result<()> {
+        let versions = releases(&["1.3.0-rc1", "1.2.1", "1.2.0", "1.1.0"])?;
+
+        assert_eq!(
+            Some(versions[1].clone()),
+            get_latest_version("latest", &versions)
+        );
+        assert_eq!(
+            Some(versions[3].clone()),
+            get_latest_version("latest:1.1", &versions)
+        );
+        assert!(get_latest_version("latest:0.15", &versions).is_none());
+        assert!(get_latest_version("1.2.0", &versions).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_version_from_tfenv_spec() -> Result<()> {
+        let versions = releases(&["1.5.7", "1.5.0", "1.4.6", "1.4.0"])?;
+
+        assert_eq!(
+            Some(versions[0].clone()),
+            get_version_from_tfenv_spec("latest", &versions, None)?
+        );
+        assert_eq!(
+            Some(versions[2].clone()),
+            get_version_from_tfenv_spec("latest:^1.4", &versions, None)?
+        );
+        assert_eq!(
+            Some(versions[3].clone()),
+            get_version_from_tfenv_spec("min-required", &versions, Some(">= 1.4.0"))?
+        );
+        assert_eq!(
+            Some(versions[2].clone()),
+            get_version_from_tfenv_spec("latest-allowed", &versions, Some("< 1.5.0"))?
+        );
+        assert!(get_version_from_tfenv_spec("min-required", &versions, None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_version_from_constraint() -> Result<()> {
+        let versions = releases(&["1.6.0", "1.5.7", "1.5.6", "1.4.0"])?;
+
+        let actual_version = get_version_from_constraint(&Args::default(), "~> 1.5.0", &versions)?;
+        assert_eq!(Some(versions[1].clone()), actual_version);
+        assert!(get_version_from_constraint(&Args::default(), "< 1.0", &versions)?.is_none());
+        let error = get_version_from_constraint(&Args::default(), "not a constraint", &versions)
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(TfswitcherError::InvalidConstraint { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_version_from_constraint_outside_policy() -> Result<()> {
+        let args = Args {
+            policy_constraint: Some(">=1.5, <1.6".to_string()),
+            ..Default::default()
+        };
+        let versions =
+            filter_versions_by_constraint(releases(&["1.6.0", "1.5.7", "1.4.0"])?, ">=1.5, <1.6")?;
+
+        assert_eq!(
+            Some(versions[0].clone()),
+            get_version_from_constraint(&args, ">=1.5", &versions)?
+        );
+        let error = get_version_from_constraint(&args, "~> 1.4.0", &versions).unwrap_err();
+        assert_eq!(
+            "no version matches both ~> 1.4.0 and the policy constraint >=1.5, <1.6",
+            error.to_string()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pick_available_without_lookup() -> Result<()> {
+        let client = Client::new();
+        let pick = |versions: &[ReleaseInfo]| Ok(versions.first().cloned());
+
+        // Releases behind a mirror are not looked up upstream
+        let mirror_args = Args {
+            mirror_url: vec!["http://mirror.invalid".to_string()],
+            ..Default::default()
+        };
+        let versions = releases(&["1.6.0", "1.5.7"])?;
+        let actual = pick_available(&client, &mirror_args, versions.clone(), pick).await?;
+        assert_eq!(Some(versions[0].clone()), actual);
+
+        // Nor are releases whose dates came from the releases API
+        let mut versions = releases(&["1.6.0", "1.5.7"])?;
+        versions[0].release_date = Some("2023-10-04T10:00:00Z".into());
+        let actual = pick_available(&client, &Args::default(), versions.clone(), pick).await?;
+        assert_eq!(Some(versions[0].clone()), actual);
+
+        assert_eq!(
+            None,
+            pick_available(&client, &Args::default(), Vec::new(), pick).await?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_version_from_constraint_prerelease_only() -> Result<()> {
+        let versions = releases(&["1.6.0-rc1", "1.6.0-beta1", "1.5.7"])?;
+        let list_all_args = Args {
+            list_all: true,
+            ..Default::default()
+        };
+
+        let actual_version =
+            get_version_from_constraint(&list_all_args, ">= 1.6.0-beta1, < 1.6.0", &versions)?;
+        assert_eq!(Some(versions[0].clone()), actual_version);
+
+        let stable_versions = releases(&["1.5.7"])?;
+        let actual_version = get_version_from_constraint(
+            &Args::default(),
+            ">= 1.6.0-beta1, < 1.6.0",
+            &stable_versions,
+        )?;
+        assert!(actual_version.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_describe_unmatched_constraint() -> Result<()> {
+        let versions = releases(&["1.5.7", "1.5.6"])?;
+        assert_eq!(
+            "No version matches constraint >= 2.0 out of 2 versions considered, the newest is 1.5.7",
+            describe_unmatched_constraint(">= 2.0", &versions)
+        );
+        assert_eq!(
+            "No version matches constraint >= 2.0 out of 0 versions considered",
+            describe_unmatched_constraint(">= 2.0", &[])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_has_prerelease_comparator() -> Result<()> {
+        assert!(has_prerelease_comparator(&VersionReq::parse(
+            ">= 1.6.0-beta1"
+        )?));
+        assert!(!has_prerelease_comparator(&VersionReq::parse("~> 1.6.0")?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_incomplete_download() {
+        let truncated = anyhow::Error::from(TfswitcherError::Truncated {
+            url: "https://example.com/terraform.zip".into(),
+            expected: 10,
+            received: 5,
+        });
+        assert!(is_incomplete_download(&truncated));
+        assert!(!is_incomplete_download(&anyhow::anyhow!(
+            "checksum mismatch for downloaded archive"
+        )));
+    }
+
+    #[test]
+    fn test_get_version_from_user_prompt_no_prompt() -> Result<()> {
+        let args = Args {
+            no_prompt: true,
+            ..Default::default()
+        };
+        let versions = releases(&["1.5.7"])?;
+        assert!(get_version_from_user_prompt(&args, &versions).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_program_name_candidates() {
+        let args = Args {
+            opentofu: true,
+            ..Default::default()
+        };
+        assert_eq!(vec!["tofu".to_string()], get_program_name_candidates(&args));
+
+        let args = Args {
+            program_names: vec!["terraform".into(), "tofu".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            vec!["terraform".to_string(), "tofu".to_string()],
+            get_program_name_candidates(&args)
+        );
+    }
+
+    #[test]
+    fn test_get_arch_x86() {
+        let expected_arch = "386";
+        let actual_arch = get_arch("x86");
+        assert_eq!(expected_arch, actual_arch);
+    }
+
+    #[test]
+    fn test_get_arch_x64_64() {
+        let expected_arch = "amd64";
+        let actual_arch = get_arch("x86_64");
+        assert_eq!(expected_arch, actual_arch);
+    }
+
+    #[test]
+    fn test_get_arch_aarch64() {
+        let expected_arch = "arm64";
+        let actual_arch = get_arch("aarch64");
+        assert_eq!(expected_arch, actual_arch);
+    }
+
+    #[test]
+    fn test_expand_path_with() -> Result<()> {
+        let home_dir = Some(PathBuf::from("/home/me"));
+        let get_var = |name: &str| (name == "WORK").then(|| "/work".to_string());
+        assert_eq!(
+            PathBuf::from("/work/cache"),
+            expand_path_with(Path::new("$WORK/cache"), home_dir.clone(), get_var)?
+        );
+        assert_eq!(
+            PathBuf::from("/home/me/bin"),
+            expand_path_with(Path::new("~/bin"), home_dir.clone(), get_var)?
+        );
+        assert!(expand_path_with(Path::new("$UNSET/cache"), home_dir, get_var).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_and_resolve_alias() -> Result<()> {
+        let tmp_dir = TempDir::new("test_save_and_resolve_alias")?;
+        let config_dir = tmp_dir.path().join("tfswitcher");
+
+        save_alias(&config_dir, "prod", "1.5.7")?;
+        save_alias(&config_dir, "@staging", "1.6.0")?;
+        save_alias(&config_dir, "prod", "1.5.6")?;
+
+        assert_eq!("1.5.6", resolve_alias(Some(&config_dir), "prod")?);
+        assert_eq!("1.6.0", resolve_alias(Some(&config_dir), "staging")?);
+        assert!(resolve_alias(Some(&config_dir), "dev").is_err());
+        assert!(save_alias(&config_dir, "loop", "@prod").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_xdg_dir_from_env() {
+        let expected_dir = env::temp_dir();
+        let actual_dir = get_xdg_dir(
+            Some(expected_dir.clone().into()),
+            Some("/home/user".into()),
+            DEFAULT_CACHE_LOCATION,
+        );
+        assert_eq!(Some(expected_dir), actual_dir);
+    }
+
+    #[test]
+    fn test_get_xdg_dir_relative_env_ignored() {
+        let expected_dir = PathBuf::from("/home/user").join(DEFAULT_CACHE_LOCATION);
+        let actual_dir = get_xdg_dir(
+            Some("relative/cache".into()),
+            Some("/home/user".into()),
+            DEFAULT_CACHE_LOCATION,
+        );
+        assert_eq!(Some(expected_dir), actual_dir);
+    }
+
+    #[test]
+    fn test_get_xdg_dir_home_dir_not_exists() {
+        let actual_dir = get_xdg_dir(None, None, DEFAULT_CACHE_LOCATION);
+        assert!(actual_dir.is_none());
+    }
+
+    #[test]
+    fn test_parse_batch_file() -> Result<()> {
+        let contents = "# fleet\n1.5.7=/opt/a/terraform\n\n 1.6.0 = /opt/b/terraform \n";
+        let expected = vec![
+            ("1.5.7".to_string(), PathBuf::from("/opt/a/terraform")),
+            ("1.6.0".to_string(), PathBuf::from("/opt/b/terraform")),
+        ];
+        assert_eq!(expected, parse_batch_file(contents)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_batch_file_invalid_entry() {
+        let error = parse_batch_file("1.5.7=/opt/a/terraform\n1.6.0\n").unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+    }
+
+    #[tokio::test]
+    async fn test_download_only_from_cache() -> Result<()> {
+        let tmp_dir = TempDir::new("test_download_only_from_cache")?;
+        let args = Args {
+            opentofu: true,
+            install_version: Some("1.6.0".to_string()),
+            cache_dir: Some(tmp_dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let client = Client::new();
+        let target = Target {
+            os: "linux".into(),
+            arch: "amd64".into(),
+            ..Default::default()
+        };
+        let zip_name = "tofu_1.6.0_linux_amd64.zip";
+        let buffer = zip_archive(&[("tofu", b"\x7fELF")])?
+            .into_inner()
+            .into_inner();
+        fs::write(tmp_dir.path().join(zip_name), &buffer)?;
+        update_cache_manifest(tmp_dir.path(), zip_name, &buffer)?;
+
+        let release = resolve_version_or_bail(&client, &args).await?;
+        assert_eq!("1.6.0", release.raw_version);
+        download_only(&client, &args, &target, &release).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_targets_names_failed_targets() -> Result<()> {
+        let args = Args {
+            targets: vec!["linux".to_string(), "/amd64".to_string()],
+            ..Default::default()
+        };
+        let release = releases(&["1.5.7"])?.remove(0);
+
+        let error = download_targets(&Client::new(), &args, &Target::default(), &release)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            "2 of the targets failed to download: linux, /amd64",
+            error.to_string()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reinstall_from_cache() -> Result<()> {
+        let tmp_dir = TempDir::new("test_reinstall_from_cache")?;
+        let cache_dir = tmp_dir.path().join("cache");
+        let args = Args {
+            os: Some("linux".to_string()),
+            arch: Some("amd64".to_string()),
+            cache_dir: Some(cache_dir.clone()),
+            ..Default::default()
+        };
+        let target = Target::from_args(&args);
+        let program_path = tmp_dir.path().join("terraform");
+        let release = releases(&["1.5.7"])?.remove(0);
+
+        let error =
+            reinstall_from_cache(&args, &target, &program_path, release.clone()).unwrap_err();
+        assert!(error.to_string().contains("is not cached"));
+        assert!(!program_path.exists());
+
+        let zip_name = release.get_zip_name(&target);
+        let buffer = zip_archive(&[("terraform", b"\x7fELFbinary")])?
+            .into_inner()
+            .into_inner();
+        fs::create_dir(&cache_dir)?;
+        fs::write(cache_dir.join(&zip_name), &buffer)?;
+        update_cache_manifest(&cache_dir, &zip_name, &buffer)?;
+
+        let installed_path = reinstall_from_cache(&args, &target, &program_path, release)?;
+        assert_eq!(b"\x7fELFbinary".to_vec(), fs::read(installed_path)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reinstall_from_cache_checks_policy() -> Result<()> {
+        let args = Args {
+            policy_constraint: Some(">=1.6".to_string()),
+            ..Default::default()
+        };
+        let release = releases(&["1.5.7"])?.remove(0);
+
+        let error =
+            reinstall_from_cache(&args, &Target::default(), Path::new("terraform"), release)
+                .unwrap_err();
+        assert!(error.to_string().contains("outside the policy constraint"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_prefetch_releases() -> Result<()> {
+        let args = Args {
+            min_version: Some("1.4.0".to_string()),
+            policy_constraint: Some("<1.7".to_string()),
+            ..Default::default()
+        };
+        let entries = parse_batch_file(
+            "1.5.7=/opt/a/terraform\n1.5.7=/opt/b/terraform\n1.3.0=/opt/c/terraform\n1.7.0=/opt/d/terraform\nlatest=/opt/e/terraform\n1.6.0=/opt/f/terraform\n",
+        )?;
+
+        let versions: Vec<_> = get_prefetch_releases(&args, &entries)
+            .into_iter()
+            .map(|release| release.raw_version)
+            .collect();
+        assert_eq!(vec!["1.5.7", "1.6.0"], versions);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_install_batch_names_failed_entries() -> Result<()> {
+        let tmp_dir = TempDir::new("test_install_batch_names_failed_entries")?;
+        let batch_path = tmp_dir.path().join("batch.txt");
+        fs::write(
+            &batch_path,
+            "one=/opt/a/terraform
+two=/opt/b/terraform
+",
+        )?;
+
+        let error = install_batch(
+            &Client::new(),
+            &Args {
+                cache_dir: Some(tmp_dir.path().join("cache")),
+                ..Default::default()
+            },
+            &Target::default(),
+            &batch_path,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            "2 of the batch entries failed to install: one -> /opt/a/terraform, two -> /opt/b/terraform",
+            error.to_string()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_from_zip_checks_version() -> Result<()> {
+        let tmp_dir = TempDir::new("test_install_from_zip_checks_version")?;
+        let zip_path = tmp_dir.path().join("terraform_1.4.0_linux_amd64.zip");
+        let program_path = tmp_dir.path().join("terraform");
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer.start_file(
+            "terraform",
+            zip::write::FileOptions::default().unix_permissions(0o755),
+        )?;
+        io::Write::write_all(&mut writer, b"\x7fELFbinary")?;
+        fs::write(&zip_path, writer.finish()?.into_inner())?;
+
+        let args = Args {
+            os: Some("linux".to_string()),
+            min_version: Some("1.5.0".to_string()),
+            ..Default::default()
+        };
+        let error = install_from_zip(&args, &program_path, &zip_path).unwrap_err();
+        assert!(error.to_string().contains("older than the minimum version"));
+        let args = Args {
+            os: Some("linux".to_string()),
+            policy_constraint: Some(">=1.5".to_string()),
+            ..Default::default()
+        };
+        let error = install_from_zip(&args, &program_path, &zip_path).unwrap_err();
+        assert!(error.to_string().contains("outside the policy constraint"));
+        assert!(!program_path.exists());
+
+        let args = Args {
+            os: Some("linux".to_string()),
+            min_version: Some("1.4.0".to_string()),
+            ..Default::default()
+        };
+        install_from_zip(&args, &program_path, &zip_path)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_single_executable() -> Result<()> {
+        let args = Args {
+            os: Some("linux".to_string()),
+            ..Default::default()
+        };
+        let elf = b"\x7fELFbinary".as_slice();
+        let executable = zip::write::FileOptions::default().unix_permissions(0o755);
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer.start_file("terraform", executable)?;
+        io::Write::write_all(&mut writer, elf)?;
+        writer.start_file("LICENSE.txt", Default::default())?;
+        io::Write::write_all(&mut writer, b"license")?;
+        let buffer = writer.finish()?.into_inner();
+        check_single_executable(&args, &mut ZipArchive::new(Cursor::new(buffer))?)?;
+
+        // Entries written without permissions are not marked as executable
+        let mut archive = zip_archive(&[("terraform", elf)])?;
+        assert!(check_single_executable(&args, &mut archive).is_err());
+        let mut archive = zip_archive(&[("LICENSE.txt", b"license")])?;
+        assert!(check_single_executable(&args, &mut archive).is_err());
+
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for name in ["terraform", "terraform-provider"] {
+            writer.start_file(name, executable)?;
+            io::Write::write_all(&mut writer, elf)?;
+        }
+        let buffer = writer.finish()?.into_inner();
+        let error =
+            check_single_executable(&args, &mut ZipArchive::new(Cursor::new(buffer))?).unwrap_err();
+        assert!(error.to_string().contains("more than one executable"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_version_from_zip_name() {
+        let actual_version =
+            get_version_from_zip_name("terraform", "terraform_1.3.0-rc1_linux_amd64.zip");
+        assert_eq!(Some("1.3.0-rc1".to_owned()), actual_version);
+    }
+
+    #[test]
+    fn test_get_version_from_zip_name_other_program() {
+        let actual_version = get_version_from_zip_name("tofu", "terraform_1.3.0_linux_amd64.zip");
+        assert!(actual_version.is_none());
+    }
+
+    #[test]
+    fn test_get_install_path_overwrite() {
+        let program_path = Path::new("bin").join("terraform");
+        let actual_path = get_install_path(InstallMode::Overwrite, &program_path, "1.0.0");
+        assert_eq!(program_path, actual_path);
+    }
+
+    #[test]
+    fn test_get_install_path_versioned() {
+        let program_path = Path::new("bin").join("terraform");
+        let expected_path = Path::new("bin").join("terraform_1.0.0");
+        let actual_path = get_install_path(InstallMode::Versioned, &program_path, "1.0.0");
+        assert_eq!(expected_path, actual_path);
+    }
+
+    #[test]
+    fn test_get_install_path_versioned_with_extension() {
+        let program_path = Path::new("bin").join("terraform.exe");
+        let expected_path = Path::new("bin").join("terraform_1.0.0.exe");
+        let actual_path = get_install_path(InstallMode::Symlink, &program_path, "1.0.0");
+        assert_eq!(expected_path, actual_path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_expand_install_path_template() -> Result<()> {
+        let target = Target {
+            os: "linux".into(),
+            arch: "amd64".into(),
+            ..Default::default()
+        };
+        let path = expand_install_path_template(
+            "/opt/{product}/{version}_{os}_{arch}/terraform",
+            ProgramName::Terraform,
+            "1.5.7",
+            &target,
+        )?;
+        assert_eq!(
+            PathBuf::from("/opt/terraform/1.5.7_linux_amd64/terraform"),
+            path
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_install_symlink() -> Result<()> {
+        let tmp_dir = TempDir::new("test_resolve_install_symlink")?;
+        let target = tmp_dir.path().join("terraform_1.5.7");
+        let link = tmp_dir.path().join("terraform");
+        fs::write(&target, "binary")?;
+        std::os::unix::fs::symlink(&target, &link)?;
+
+        assert!(resolve_install_symlink(&link, false).is_err());
+        assert_eq!(
+            fs::canonicalize(&target)?,
+            resolve_install_symlink(&link, true)?
+        );
+        assert_eq!(target, resolve_install_symlink(&target, false)?);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_symlink_replaces_existing() -> Result<()> {
+        let tmp_dir = TempDir::new("test_create_symlink_replaces_existing")?;
+        let target = tmp_dir.path().join("terraform_1.0.0");
+        let link = tmp_dir.path().join("terraform");
+        File::create(&target)?;
+        File::create(&link)?;
+
+        create_symlink(&target, &link)?;
+        assert_eq!(target, fs::read_link(&link)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_os_macos() {
+        assert_eq!("darwin", get_os("macos"));
+    }
+
+    #[test]
+    fn test_get_os_freebsd() {
+        assert_eq!("freebsd", get_os("freebsd"));
+    }
+
+    #[test]
+    fn test_is_supported_platform() {
+        assert!(is_supported_platform("openbsd", "amd64"));
+        assert!(is_supported_platform("solaris", "amd64"));
+        assert!(!is_supported_platform("openbsd", "arm64"));
+        assert!(!is_supported_platform("netbsd", "amd64"));
+    }
+
+    #[test]
+    fn test_get_cached_zip_not_exists() -> Result<()> {
+        const ZIP_NAME: &str = "test_archive.zip";
+
+        let tmp_dir = TempDir::new("test_get_cached_zip_not_exists")?;
+
+        let file = get_cached_zip(Some(tmp_dir.path()), ZIP_NAME)?;
+        assert!(file.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_cached_zip_home_dir_not_exists() -> Result<()> {
+        let file = get_cached_zip(None, "")?;
+        assert!(file.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_cached_zip_exists() -> Result<()> {
+        const ZIP_NAME: &str = "test_archive.zip";
+
+        let tmp_dir = TempDir::new("test_get_cached_zip_exists")?;
+        let file_path = tmp_dir.path().join(ZIP_NAME);
+        File::create(file_path)?;
+
+        let file = get_cached_zip(Some(tmp_dir.path()), ZIP_NAME)?;
+        assert!(file.is_some());
+
+        Ok(())
+    }
 
     #[test]
-    fn test_parse_config_arguments_bool_flags_disabled_from_cli() -> Result<()> {
-        let config_file = r#"list_all = true
-opentofu = true"#;
+    fn test_get_valid_cached_zip_corrupt() -> Result<()> {
+        const ZIP_NAME: &str = "test_archive.zip";
 
-        let tmp_dir = TempDir::new("test_parse_config_arguments_bool_flags_disabled_from_cli")?;
-        let tmp_dir_path = tmp_dir.path();
-        let file_path = tmp_dir_path.join(CONFIG_FILE_NAME);
-        fs::write(file_path, config_file)?;
+        let tmp_dir = TempDir::new("test_get_valid_cached_zip_corrupt")?;
+        let file_path = tmp_dir.path().join(ZIP_NAME);
+        fs::write(&file_path, "not a zip archive")?;
 
-        let mut args = Args::default();
-        parse_config_arguments(tmp_dir_path.to_path_buf(), &mut args)?;
-        assert!(args.list_all);
-        assert!(args.opentofu);
+        let archive = get_valid_cached_zip(Some(tmp_dir.path()), ZIP_NAME, None, false)?;
+        assert!(archive.is_none());
+        assert!(!file_path.exists());
 
         Ok(())
     }
 
     #[test]
-    fn test_parse_config_arguments_bool_flags_enabled_from_cli() -> Result<()> {
-        let tmp_dir = TempDir::new("test_parse_config_arguments_bool_flags_enabled_from_cli")?;
-        let tmp_dir_path = tmp_dir.path();
-        let file_path = tmp_dir_path.join(CONFIG_FILE_NAME);
-        File::create(file_path)?;
+    fn test_get_valid_cached_zip_checksum_mismatch() -> Result<()> {
+        const ZIP_NAME: &str = "test_archive.zip";
 
-        let mut args = Args {
-            list_all: true,
-            opentofu: true,
-            ..Default::default()
-        };
-        parse_config_arguments(tmp_dir_path.to_path_buf(), &mut args)?;
-        assert!(args.list_all);
-        assert!(args.opentofu);
+        let tmp_dir = TempDir::new("test_get_valid_cached_zip_checksum_mismatch")?;
+        let file_path = tmp_dir.path().join(ZIP_NAME);
+        fs::write(&file_path, "not a zip archive")?;
+
+        let result = get_valid_cached_zip(
+            Some(tmp_dir.path()),
+            ZIP_NAME,
+            Some(&Checksum::Sha256([0; 32])),
+            true,
+        );
+        assert!(result.is_err());
+        assert!(file_path.exists());
+
+        let archive = get_valid_cached_zip(
+            Some(tmp_dir.path()),
+            ZIP_NAME,
+            Some(&Checksum::Sha256([0; 32])),
+            false,
+        )?;
+        assert!(archive.is_none());
+        assert!(!file_path.exists());
 
         Ok(())
     }
 
     #[test]
-    fn test_load_config_file_in_cwd() -> Result<()> {
-        let expected_config_file = Args {
-            binary_location: Some("test_load_config_file_in_cwd".into()),
-            list_all: true,
-            opentofu: true,
-            install_version: Some("test_load_config_file_in_cwd".to_owned()),
-            generator: None,
-        };
-        let config_file = r#"bin = "test_load_config_file_in_cwd"
-list_all = true
-opentofu = true
-version = "test_load_config_file_in_cwd""#;
+    fn test_get_shared_cached_zip_checksum_mismatch_kept() -> Result<()> {
+        const ZIP_NAME: &str = "test_archive.zip";
 
-        let tmp_dir = TempDir::new("test_load_config_file_in_cwd")?;
-        let tmp_dir_path = tmp_dir.path();
-        let file_path = tmp_dir_path.join(CONFIG_FILE_NAME);
-        fs::write(file_path, config_file)?;
+        let tmp_dir = TempDir::new("test_get_shared_cached_zip_checksum_mismatch_kept")?;
+        let file_path = tmp_dir.path().join(ZIP_NAME);
+        fs::write(&file_path, "not a zip archive")?;
 
-        let actual_config_file = load_config_file(tmp_dir_path.to_path_buf(), None)?;
-        assert_eq!(Some(expected_config_file), actual_config_file);
+        let archive = get_shared_cached_zip(
+            Some(tmp_dir.path()),
+            ZIP_NAME,
+            Some(&Checksum::Sha256([0; 32])),
+            false,
+        )?;
+        assert!(archive.is_none());
+        assert!(file_path.exists());
 
         Ok(())
     }
 
     #[test]
-    fn test_load_config_file_in_home() -> Result<()> {
-        let expected_config_file = Args {
-            binary_location: Some("test_load_config_file_in_home".into()),
-            list_all: true,
-            opentofu: true,
-            install_version: Some("test_load_config_file_in_home".to_owned()),
-            generator: None,
+    fn test_get_valid_cached_zip_manifest_mismatch() -> Result<()> {
+        const ZIP_NAME: &str = "test_archive.zip";
+
+        let tmp_dir = TempDir::new("test_get_valid_cached_zip_manifest_mismatch")?;
+        let buffer = zip_archive(&[("terraform", b"binary")])?
+            .into_inner()
+            .into_inner();
+        cache_zip_archive(&mut tmp_dir.path().to_path_buf(), ZIP_NAME, &buffer)?;
+
+        let archive = get_valid_cached_zip(Some(tmp_dir.path()), ZIP_NAME, None, false)?;
+        assert!(archive.is_some());
+
+        let file_path = tmp_dir.path().join(ZIP_NAME);
+        fs::write(
+            &file_path,
+            zip_archive(&[("terraform", b"tampered")])?
+                .into_inner()
+                .into_inner(),
+        )?;
+        let archive = get_valid_cached_zip(Some(tmp_dir.path()), ZIP_NAME, None, false)?;
+        assert!(archive.is_none());
+        assert!(!file_path.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_cache_manifest_replaces_entry() -> Result<()> {
+        let tmp_dir = TempDir::new("test_update_cache_manifest_replaces_entry")?;
+        update_cache_manifest(tmp_dir.path(), "a.zip", b"first")?;
+        update_cache_manifest(tmp_dir.path(), "b.zip", b"other")?;
+        update_cache_manifest(tmp_dir.path(), "a.zip", b"second")?;
+
+        let contents = fs::read_to_string(tmp_dir.path().join(CACHE_MANIFEST_FILE_NAME))?;
+        assert_eq!(2, contents.lines().count());
+        let checksum = get_manifest_checksum(tmp_dir.path(), "a.zip").unwrap();
+        assert!(checksum.verify(b"second"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_target() -> Result<()> {
+        assert_eq!(
+            ("darwin".to_owned(), "arm64".to_owned()),
+            parse_target("darwin/arm64")?
+        );
+        assert_eq!(
+            ("linux".to_owned(), "amd64".to_owned()),
+            parse_target("linux/x86_64")?
+        );
+        assert!(parse_target("linux").is_err());
+        assert!(parse_target("/amd64").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_and_import_cache() -> Result<()> {
+        let source_dir = TempDir::new("test_export_cache")?;
+        let zip_name = "terraform_1.5.7_linux_amd64.zip";
+        fs::write(source_dir.path().join(zip_name), "archive")?;
+        update_cache_manifest(source_dir.path(), zip_name, b"archive")?;
+        fs::create_dir(source_dir.path().join(BINARY_CACHE_DIR_NAME))?;
+        fs::write(
+            source_dir
+                .path()
+                .join(BINARY_CACHE_DIR_NAME)
+                .join("terraform_1.5.7_linux_amd64"),
+            "binary",
+        )?;
+        update_cache_manifest(
+            source_dir.path(),
+            "binaries/terraform_1.5.7_linux_amd64",
+            b"binary",
+        )?;
+        let tar_path = source_dir.path().join("cache.tar");
+        export_cache(source_dir.path(), &tar_path)?;
+
+        let tarball = fs::read(&tar_path)?;
+        let names: Vec<_> = read_tar_entries(&tarball)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(
+            vec![
+                CACHE_MANIFEST_FILE_NAME,
+                "binaries/terraform_1.5.7_linux_amd64",
+                zip_name
+            ],
+            names
+        );
+
+        let cache_dir = TempDir::new("test_import_cache")?;
+        import_cache(&Args::default(), cache_dir.path(), &tar_path)?;
+        // Importing again overwrites the archive, which needs confirmation
+        let args = Args {
+            no_prompt: true,
+            ..Default::default()
         };
-        let config_file = r#"bin = "test_load_config_file_in_home"
-list_all = true
-opentofu = true
-version = "test_load_config_file_in_home""#;
+        assert!(import_cache(&args, cache_dir.path(), &tar_path).is_err());
+        let args = Args {
+            yes: true,
+            ..Default::default()
+        };
+        import_cache(&args, cache_dir.path(), &tar_path)?;
+        assert_eq!(
+            b"archive".to_vec(),
+            fs::read(cache_dir.path().join(zip_name))?
+        );
+        assert!(get_manifest_checksum(cache_dir.path(), zip_name).is_some());
+        assert_eq!(
+            b"binary".to_vec(),
+            fs::read(
+                cache_dir
+                    .path()
+                    .join(BINARY_CACHE_DIR_NAME)
+                    .join("terraform_1.5.7_linux_amd64")
+            )?
+        );
 
-        let tmp_dir = TempDir::new("test_load_config_file_in_home")?;
-        let tmp_dir_path = tmp_dir.path();
-        let file_path = tmp_dir_path.join(CONFIG_FILE_NAME);
-        fs::write(file_path, config_file)?;
+        Ok(())
+    }
 
-        let actual_config_file = load_config_file(".".into(), Some(tmp_dir_path.to_path_buf()))?;
-        assert_eq!(Some(expected_config_file), actual_config_file);
+    #[test]
+    fn test_import_cache_rejects_mismatched_checksum() -> Result<()> {
+        let tmp_dir = TempDir::new("test_import_cache_rejects_mismatched_checksum")?;
+        let zip_name = "terraform_1.5.7_linux_amd64.zip";
+        let tar_path = tmp_dir.path().join("cache.tar");
+        let mut tarball = Vec::new();
+        write_tar_entry(
+            &mut tarball,
+            CACHE_MANIFEST_FILE_NAME,
+            format_checksum_line(b"archive", zip_name).as_bytes(),
+        )?;
+        write_tar_entry(&mut tarball, zip_name, b"tampered")?;
+        write_tar_entry(&mut tarball, "../escape.zip", b"archive")?;
+        tarball.extend([0; TAR_BLOCK_SIZE * 2]);
+        fs::write(&tar_path, &tarball)?;
+
+        let cache_dir = tmp_dir.path().join("cache");
+        assert!(import_cache(&Args::default(), &cache_dir, &tar_path).is_err());
+        assert!(!cache_dir.join(zip_name).exists());
+
+        assert!(read_tar_entries(&tarball[..TAR_BLOCK_SIZE + 10]).is_err());
+        assert!(read_tar_entries(&[1; TAR_BLOCK_SIZE * 2]).is_err());
 
         Ok(())
     }
 
+    #[cfg(unix)]
     #[test]
-    fn test_load_config_file_not_present() -> Result<()> {
-        let tmp_dir = TempDir::new("test_load_config_file_not_present")?;
-        let tmp_dir_path = tmp_dir.path();
+    fn test_check_disk_space() -> Result<()> {
+        let tmp_dir = TempDir::new("test_check_disk_space")?;
+        let missing_dir = tmp_dir.path().join("not").join("created");
 
-        let actual_config_file = load_config_file(".".into(), Some(tmp_dir_path.to_path_buf()))?;
-        assert!(actual_config_file.is_none());
+        check_disk_space(&missing_dir, 1)?;
+        let err = check_disk_space(&missing_dir, u64::MAX).unwrap_err();
+        assert!(err.to_string().starts_with("insufficient disk space"));
 
         Ok(())
     }
 
     #[test]
-    fn test_capture_terraform_versions() -> Result<()> {
-        let expected_versions: Vec<ReleaseInfo> =
-            vec!["1.3.0", "1.2.0", "1.1.0", "1.0.0", "0.15.0"]
-                .into_iter()
-                .map(|v| ReleaseInfo::new(ProgramName::Terraform, v.into()))
-                .collect();
-        let actual_versions = capture_terraform_versions(&Args::default(), &LINES);
+    fn test_get_prune_candidates() -> Result<()> {
+        let tmp_dir = TempDir::new("test_get_prune_candidates")?;
+        let zip_path = tmp_dir.path().join("terraform_1.5.7_linux_amd64.zip");
+        let manifest_path = tmp_dir.path().join(CACHE_MANIFEST_FILE_NAME);
+        fs::write(&zip_path, "archive")?;
+        fs::write(&manifest_path, "checksums")?;
+        fs::write(tmp_dir.path().join("notes.txt"), "unrelated")?;
+        let binary_path = tmp_dir
+            .path()
+            .join(BINARY_CACHE_DIR_NAME)
+            .join("terraform_1.5.7_linux_amd64");
+        fs::create_dir(tmp_dir.path().join(BINARY_CACHE_DIR_NAME))?;
+        fs::write(&binary_path, "binary")?;
 
-        assert_eq!(expected_versions, actual_versions);
+        let candidates = get_prune_candidates(tmp_dir.path())?;
+        assert_eq!(
+            vec![manifest_path.clone(), binary_path, zip_path.clone()],
+            candidates
+        );
+
+        remove_files(&candidates, true)?;
+        assert!(zip_path.exists());
+        remove_files(&candidates, false)?;
+        assert!(!zip_path.exists());
+        assert!(!manifest_path.exists());
 
         Ok(())
     }
 
+    #[cfg(unix)]
     #[test]
-    fn test_capture_terraform_versions_list_all() -> Result<()> {
-        let expected_versions: Vec<ReleaseInfo> = vec![
-            "1.3.0",
-            "1.3.0-rc1",
-            "1.3.0-beta1",
-            "1.3.0-alpha20220608",
-            "1.2.0",
-            "1.2.0-rc1",
-            "1.2.0-beta1",
-            "1.2.0-alpha20220413",
-            "1.2.0-alpha-20220328",
-            "1.1.0",
-            "1.1.0-rc1",
-            "1.1.0-beta1",
-            "1.1.0-alpha20211029",
-            "1.0.0",
-            "0.15.0",
-            "0.15.0-rc1",
-            "0.15.0-beta1",
-            "0.15.0-alpha20210107",
-        ]
-        .into_iter()
-        .map(|v| ReleaseInfo::new(ProgramName::Terraform, v.into()))
-        .collect();
+    fn test_run_pre_download_hook() -> Result<()> {
+        let target = Target {
+            os: "linux".into(),
+            arch: "amd64".into(),
+            ..Default::default()
+        };
+        let release = ReleaseInfo::new(ProgramName::Terraform, "1.5.7".into())?;
+
+        run_pre_download_hook(
+            r#"test "$TFSWITCHER_PRODUCT $TFSWITCHER_VERSION $TFSWITCHER_OS/$TFSWITCHER_ARCH" = "terraform 1.5.7 linux/amd64""#,
+            &target,
+            &release,
+        )?;
+        assert!(run_pre_download_hook("exit 1", &target, &release).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_cached_versions() -> Result<()> {
+        let cache_dir = TempDir::new("test_get_cached_versions")?;
+        let shared_dir = TempDir::new("test_get_cached_versions_shared")?;
+        for name in [
+            "terraform_1.4.0_linux_amd64.zip",
+            "terraform_1.5.7_linux_amd64.zip",
+            "terraform_1.6.0-rc1_linux_amd64.zip",
+            "terraform_1.6.0_darwin_arm64.zip",
+            "tofu_1.6.0_linux_amd64.zip",
+            "SHA256SUMS",
+        ] {
+            fs::write(cache_dir.path().join(name), "archive")?;
+        }
+        fs::write(
+            shared_dir.path().join("terraform_1.5.7_linux_amd64.zip"),
+            "archive",
+        )?;
+        let cache_dirs = [shared_dir.path(), cache_dir.path()];
+        let target = Target {
+            os: "linux".into(),
+            arch: "amd64".into(),
+            ..Default::default()
+        };
+
+        let versions = get_cached_versions(&cache_dirs, &Args::default(), &target)?;
+        assert_eq!(vec!["1.5.7", "1.4.0"], versions.get_versions());
+
         let args = Args {
             list_all: true,
             ..Default::default()
         };
-        let actual_versions = capture_terraform_versions(&args, &LINES);
+        let versions = get_cached_versions(&cache_dirs, &args, &target)?;
+        assert_eq!(vec!["1.6.0-rc1", "1.5.7", "1.4.0"], versions.get_versions());
 
-        assert_eq!(expected_versions, actual_versions);
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_cached_binary() -> Result<()> {
+        let tmp_dir = TempDir::new("test_get_cached_binary")?;
+        let args = Args {
+            cache_binaries: true,
+            cache_dir: Some(tmp_dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let target = Target {
+            os: "linux".to_string(),
+            arch: "amd64".to_string(),
+            ..Default::default()
+        };
+        let release = releases(&["1.5.7"])?.remove(0);
+        let binary_path = tmp_dir
+            .path()
+            .join(BINARY_CACHE_DIR_NAME)
+            .join("terraform_1.5.7_linux_amd64");
+        fs::create_dir(binary_path.parent().unwrap())?;
+        fs::write(&binary_path, "binary")?;
+        let manifest_name = get_binary_manifest_name(&args, &target, "1.5.7");
+        update_cache_manifest(tmp_dir.path(), &manifest_name, b"binary")?;
+
+        assert_eq!(
+            Some(b"binary".to_vec()),
+            get_cached_binary(&args, &target, &release)
+        );
+        let verify_args = Args {
+            verify: true,
+            cache_binaries: true,
+            cache_dir: args.cache_dir.clone(),
+            ..Default::default()
+        };
+        assert_eq!(None, get_cached_binary(&verify_args, &target, &release));
+        let extract_all_args = Args {
+            extract_all: true,
+            ..args.clone()
+        };
+        assert_eq!(
+            None,
+            get_cached_binary(&extract_all_args, &target, &release)
+        );
+        let entry_args = Args {
+            entry_name: Some("bin/terraform-ent".to_string()),
+            ..args.clone()
+        };
+        assert_eq!(
+            "terraform_1.5.7_linux_amd64_bin-terraform-ent",
+            get_binary_cache_name(&entry_args, &target, "1.5.7")
+        );
+        assert_eq!(None, get_cached_binary(&entry_args, &target, &release));
+
+        // A binary that doesn't match the manifest is removed
+        fs::write(&binary_path, "tampered")?;
+        assert_eq!(None, get_cached_binary(&args, &target, &release));
+        assert!(!binary_path.exists());
 
         Ok(())
     }
 
     #[test]
-    fn test_get_version_from_module() -> Result<()> {
-        let expected_release = ReleaseInfo::new(ProgramName::Terraform, "1.0.0".into());
-        let versions = vec![expected_release.clone()];
+    fn test_install_cached_binary() -> Result<()> {
+        let tmp_dir = TempDir::new("test_install_cached_binary")?;
+        let program_path = tmp_dir.path().join("terraform");
 
-        let tmp_dir = TempDir::new("test_get_version_from_module")?;
-        let tmp_dir_path = tmp_dir.path();
-        let file_path = tmp_dir_path.join("version.tf");
-        fs::write(file_path, r#"terraform { required_version = "~>1.0.0" }"#)?;
+        install_cached_binary(&Args::default(), &program_path, "1.5.7", b"binary")?;
+        assert_eq!("binary", fs::read_to_string(&program_path)?);
 
-        let actual_version = get_version_from_module(tmp_dir_path, &versions)?;
-        assert_eq!(Some(expected_release), actual_version);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_atomically() -> Result<()> {
+        let tmp_dir = TempDir::new("test_write_atomically")?;
+        let path = tmp_dir.path().join("terraform_1.5.7_linux_amd64.zip");
+
+        write_atomically(&path, "archive")?;
+        assert_eq!("archive", fs::read_to_string(&path)?);
+        assert_eq!(1, fs::read_dir(tmp_dir.path())?.count());
 
         Ok(())
     }
 
     #[test]
-    fn test_get_arch_x86() {
-        let expected_arch = "386";
-        let actual_arch = get_arch("x86");
-        assert_eq!(expected_arch, actual_arch);
+    fn test_remove_stale_temp_files() -> Result<()> {
+        let tmp_dir = TempDir::new("test_remove_stale_temp_files")?;
+        let stale_path = tmp_dir
+            .path()
+            .join(format!("{TEMP_FILE_PREFIX}1-stale.zip"));
+        let fresh_path = tmp_dir
+            .path()
+            .join(format!("{TEMP_FILE_PREFIX}2-fresh.zip"));
+        let other_path = tmp_dir.path().join("old.zip");
+        for path in [&stale_path, &fresh_path, &other_path] {
+            fs::write(path, "contents")?;
+        }
+        let old = std::time::SystemTime::now() - STALE_TEMP_FILE_AGE * 2;
+        for path in [&stale_path, &other_path] {
+            File::options().write(true).open(path)?.set_modified(old)?;
+        }
+
+        remove_stale_temp_files(tmp_dir.path());
+        assert!(!stale_path.exists());
+        assert!(fresh_path.exists());
+        assert!(other_path.exists());
+
+        Ok(())
     }
 
     #[test]
-    fn test_get_arch_x64_64() {
-        let expected_arch = "amd64";
-        let actual_arch = get_arch("x86_64");
-        assert_eq!(expected_arch, actual_arch);
+    fn test_get_default_location() {
+        assert_eq!("bin", get_default_location(Some("bin".into())));
+        assert_eq!(DEFAULT_LOCATION, get_default_location(None));
+        assert_eq!(DEFAULT_LOCATION, get_default_location(Some("".into())));
+        assert_eq!(
+            DEFAULT_LOCATION,
+            get_default_location(Some("/usr/bin".into()))
+        );
     }
 
     #[test]
-    fn test_get_arch_aarch64() {
-        let expected_arch = "arm64";
-        let actual_arch = get_arch("aarch64");
-        assert_eq!(expected_arch, actual_arch);
+    fn test_is_proc_translated() {
+        assert!(is_proc_translated("1\n"));
+        assert!(!is_proc_translated("0\n"));
+        assert!(!is_proc_translated(""));
     }
 
     #[test]
-    fn test_get_cached_zip_not_exists() -> Result<()> {
+    fn test_describe_create_error() {
+        let path = Path::new("/usr/local/bin/terraform");
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert!(describe_create_error(path, &denied).contains("elevated privileges"));
+
+        let not_found = io::Error::from(io::ErrorKind::NotFound);
+        assert_eq!(
+            "failed to create file at \"/usr/local/bin/terraform\"",
+            describe_create_error(path, &not_found)
+        );
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!("512 B", format_size(512));
+        assert_eq!("1.5 KiB", format_size(1536));
+        assert_eq!("20.0 MiB", format_size(20 * 1024 * 1024));
+    }
+
+    #[test]
+    fn test_remove_cached_zip() -> Result<()> {
         const ZIP_NAME: &str = "test_archive.zip";
 
-        let tmp_dir = TempDir::new("test_get_cached_zip_not_exists")?;
+        let tmp_dir = TempDir::new("test_remove_cached_zip")?;
+        let file_path = tmp_dir.path().join(ZIP_NAME);
+        File::create(&file_path)?;
 
-        let file = get_cached_zip(Some(&mut tmp_dir.path().to_path_buf()), ZIP_NAME)?;
-        assert!(file.is_none());
+        remove_cached_zip(Some(tmp_dir.path()), ZIP_NAME)?;
+        assert!(!file_path.exists());
 
         Ok(())
     }
 
     #[test]
-    fn test_get_cached_zip_home_dir_not_exists() -> Result<()> {
-        let file = get_cached_zip(None, "")?;
-        assert!(file.is_none());
+    fn test_parse_platforms() -> Result<()> {
+        let release = ReleaseInfo::new(ProgramName::Terraform, "1.5.7".into())?;
+        let checksums = "aaaa  terraform_1.5.7_linux_amd64.zip\n\
+            bbbb  terraform_1.5.7_darwin_arm64.zip\n\
+            cccc  terraform_1.5.7_linux_amd64.tar.gz\n";
+        assert_eq!(
+            vec!["darwin/arm64".to_string(), "linux/amd64".to_string()],
+            parse_platforms(&release, checksums)
+        );
 
         Ok(())
     }
 
     #[test]
-    fn test_get_cached_zip_exists() -> Result<()> {
-        const ZIP_NAME: &str = "test_archive.zip";
+    fn test_get_opentofu_signing_identity() -> Result<()> {
+        assert_eq!(
+            "https://github.com/opentofu/opentofu/.github/workflows/release.yml@refs/heads/v1.6",
+            get_opentofu_signing_identity(&Version::parse("1.6.2")?)
+        );
 
-        let tmp_dir = TempDir::new("test_get_cached_zip_exists")?;
-        let cache_dir = tmp_dir.path().join(DEFAULT_CACHE_LOCATION);
-        let file_path = cache_dir.join(ZIP_NAME);
-        fs::create_dir_all(cache_dir)?;
-        File::create(file_path)?;
+        Ok(())
+    }
 
-        let file = get_cached_zip(Some(&mut tmp_dir.path().to_path_buf()), ZIP_NAME)?;
-        assert!(file.is_some());
+    #[test]
+    fn test_find_checksum() {
+        let contents =
+            "abc123  terraform_1.0.0_linux_amd64.zip\ndef456  terraform_1.0.0_darwin_arm64.zip\n";
+        let actual_checksum = find_checksum(contents, "terraform_1.0.0_darwin_arm64.zip");
+        assert_eq!(Some("def456"), actual_checksum);
+    }
+
+    #[test]
+    fn test_find_checksum_exact_match() {
+        let contents = "\
+1111111111111111111111111111111111111111111111111111111111111111  terraform_1.5.7_darwin_amd64.zip
+2222222222222222222222222222222222222222222222222222222222222222  terraform_1.5.7_darwin_arm64.zip
+3333333333333333333333333333333333333333333333333333333333333333  terraform_1.5.7_linux_amd64.zip.sig
+4444444444444444444444444444444444444444444444444444444444444444  terraform_1.5.7_linux_amd64.zip\r
+5555555555555555555555555555555555555555555555555555555555555555 *terraform_1.5.7_linux_arm64.zip
+";
+
+        assert_eq!(
+            Some("2222222222222222222222222222222222222222222222222222222222222222"),
+            find_checksum(contents, "terraform_1.5.7_darwin_arm64.zip")
+        );
+        assert_eq!(
+            Some("4444444444444444444444444444444444444444444444444444444444444444"),
+            find_checksum(contents, "terraform_1.5.7_linux_amd64.zip")
+        );
+        assert_eq!(
+            Some("5555555555555555555555555555555555555555555555555555555555555555"),
+            find_checksum(contents, "terraform_1.5.7_linux_arm64.zip")
+        );
+        assert!(find_checksum(contents, "terraform_1.5.7_linux_amd64").is_none());
+        assert!(find_checksum(contents, "1.5.7_linux_amd64.zip").is_none());
+    }
+
+    #[test]
+    fn test_find_checksum_not_present() {
+        let contents = "abc123  terraform_1.0.0_linux_amd64.zip\n";
+        let actual_checksum = find_checksum(contents, "terraform_1.0.0_windows_amd64.zip");
+        assert!(actual_checksum.is_none());
+    }
+
+    #[test]
+    fn test_checksum_sha256() -> Result<()> {
+        const EXPECTED: &str = "4e23289ccb77856fb7ad46a3b6d180385c6ccf0493f0a5d440defb7736668a3a";
+        let checksum = Checksum::from_hex(EXPECTED)?;
+        assert!(matches!(checksum, Checksum::Sha256(_)));
+        assert!(checksum.verify(b"tfswitcher"));
+        assert!(!checksum.verify(b"terraform"));
+        assert_eq!(EXPECTED, checksum.to_string());
 
         Ok(())
     }
 
+    #[test]
+    fn test_checksum_sha512() -> Result<()> {
+        const EXPECTED: &str = "8a561e7bb32a0e5291a6acd5d637572ef0b811e6c89f24d11ddace4222d26babc91ae0aaa6759b155d251c25417e4f9b2ec681c9cff3c98e83ae577dd624ba82";
+        let checksum = Checksum::from_hex(&EXPECTED.to_uppercase())?;
+        assert!(matches!(checksum, Checksum::Sha512(_)));
+        assert!(checksum.verify(b"tfswitcher"));
+        assert_eq!(EXPECTED, checksum.to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_checksum_invalid() {
+        assert!(Checksum::from_hex("abc").is_err());
+        assert!(Checksum::from_hex("zz").is_err());
+        assert!(Checksum::from_hex("abcd").is_err());
+    }
+
     #[test]
     fn test_cache_zip_file() -> Result<()> {
         const ZIP_NAME: &str = "test_archive.zip";
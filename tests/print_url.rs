@@ -0,0 +1,51 @@
+use std::{fs, process::Command};
+
+use tempdir::TempDir;
+
+#[test]
+fn test_print_url_keeps_stdout_to_the_url() -> anyhow::Result<()> {
+    let tmp_dir = TempDir::new("test_print_url_keeps_stdout_to_the_url")?;
+    fs::write(tmp_dir.path().join(".tool-versions"), "terraform 1.5.7\n")?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tfswitcher"))
+        .args(["--print-url", "--os", "linux", "--arch", "amd64"])
+        .current_dir(tmp_dir.path())
+        .env("HOME", tmp_dir.path())
+        .env("XDG_CONFIG_HOME", tmp_dir.path())
+        .env_remove("TF_VERSION")
+        .env_remove("TFENV_TERRAFORM_VERSION")
+        .output()?;
+
+    assert!(output.status.success());
+    assert_eq!(
+        "https://releases.hashicorp.com/terraform/1.5.7/terraform_1.5.7_linux_amd64.zip\n",
+        String::from_utf8(output.stdout)?
+    );
+    assert!(String::from_utf8(output.stderr)?.contains(".tool-versions"));
+
+    Ok(())
+}
+
+#[test]
+fn test_env_file_wins_over_tfenv_variable() -> anyhow::Result<()> {
+    let tmp_dir = TempDir::new("test_env_file_wins_over_tfenv_variable")?;
+    fs::write(tmp_dir.path().join(".env"), "TERRAFORM_VERSION=1.5.7\n")?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tfswitcher"))
+        .args(["--print-url", "--os", "linux", "--arch", "amd64"])
+        .args(["--env-file", ".env"])
+        .current_dir(tmp_dir.path())
+        .env("HOME", tmp_dir.path())
+        .env("XDG_CONFIG_HOME", tmp_dir.path())
+        .env_remove("TF_VERSION")
+        .env("TFENV_TERRAFORM_VERSION", "1.4.0")
+        .output()?;
+
+    assert!(output.status.success());
+    assert_eq!(
+        "https://releases.hashicorp.com/terraform/1.5.7/terraform_1.5.7_linux_amd64.zip\n",
+        String::from_utf8(output.stdout)?
+    );
+
+    Ok(())
+}